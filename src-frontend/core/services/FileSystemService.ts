@@ -6,7 +6,29 @@ export interface FileEntry {
   is_dir: boolean;
 }
 
+/** Error of the file commands; `path-outside-workspace` when a path leaves the open project. */
+export type WorkspaceError =
+  | { kind: 'no-workspace' }
+  | { kind: 'path-outside-workspace', path: string }
+  | { kind: 'failed', message: string };
+
+/** Result of `save_incoming_project`; `skipped` lists entries that could have escaped the project. */
+export interface ImportReport {
+  written: number;
+  deleted: number;
+  skipped: { path: string, reason: 'unsafe-path' | 'device-path' | 'symlink' | 'not-a-file' }[];
+}
+
 export class FileSystemService {
+  /**
+   * Lets the user pick a project folder with the native dialog and confines the
+   * file commands to it (plus the plugins and settings folders). Resolves to
+   * the folder, or null if the user cancelled.
+   */
+  async openWorkspace(title: string): Promise<string | null> {
+    return invoke<string | null>("open_workspace", { title });
+  }
+
   async readDirectory(path: string): Promise<FileEntry[]> {
     return invoke<FileEntry[]>("read_directory", { path });
   }
@@ -51,7 +73,8 @@ export class FileSystemService {
     return invoke("git_pull", { path, sshKeyPath });
   }
 
-  async saveIncomingProject(destPath: string, data: number[]): Promise<void> {
-    return invoke("save_incoming_project", { destPath, data });
+  /** Moves the project received in transfer `transferId` (the host's peer id) to `destPath`. */
+  async saveIncomingProject(destPath: string, transferId: string): Promise<ImportReport> {
+    return invoke<ImportReport>("save_incoming_project", { destPath, transferId });
   }
 }
\ No newline at end of file
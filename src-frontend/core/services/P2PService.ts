@@ -2,6 +2,80 @@ import { invoke } from "@tauri-apps/api/core";
 import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import { EventEmitter } from "./EventEmitter";
 
+export interface SessionPeer {
+  peer_id: string;
+  role: 'host' | 'guest' | 'relay' | 'unknown';
+  session_role: SessionRole;
+  transport: 'direct' | 'relayed' | 'hole-punched';
+  app_version: string | null;
+  /** Features both we and the peer support, e.g. 'binary-codec'. */
+  capabilities: string[];
+  /** Profile the guest joined with; null for the host. */
+  profile: JoinProfile | null;
+}
+
+/** What a member may do in a session; viewers cannot edit. */
+export type SessionRole = 'host' | 'editor' | 'viewer';
+
+/** Payload of `permission-denied`: something a peer sent that we dropped. */
+export interface PermissionDenied {
+  peer_id: string;
+  action: 'sync' | 'file-content' | 'read';
+  path: string;
+  reason: string;
+}
+
+export interface JoinProfile {
+  display_name: string;
+  /** Hex color such as '#89b4fa'. */
+  color: string;
+  avatar: number[] | null;
+  message: string;
+}
+
+/** Payload of `join-requested`. */
+export interface JoinRequest {
+  peer_id: string;
+  profile: JoinProfile;
+  app_version: string;
+}
+
+/** Payload of `session-ended`: the host removed us from its session. */
+export interface SessionEnded {
+  peer_id: string;
+  reason: string;
+}
+
+/** An entry of the persisted trust list. */
+export interface TrustedPeer {
+  peer_id: string;
+  /** Trusted peers join without a prompt; blocked ones are refused. */
+  level: 'trusted' | 'blocked';
+  note: string;
+}
+
+export interface AwarenessEntry {
+  client_id: number;
+  clock: number;
+  state: string | null;
+}
+
+export interface DiscoveredPeer {
+  peer_id: string;
+  addrs: string[];
+  session: string | null;
+}
+
+export interface TransferProgress {
+  peer_id: string;
+  direction: 'send' | 'receive';
+  files_done: number;
+  files_total: number;
+  bytes_done: number;
+  bytes_total: number;
+  error: string | null;
+}
+
 /**
  * TransportLayer
  * Responsible strictly for sending/receiving packets and managing network identity.
@@ -39,18 +113,57 @@ export class P2PService extends EventEmitter {
           this.emit('identity-updated', { peerId: this.myPeerId, addresses: this.myAddresses });
         }
       }),
-      await listen("peer-connected", () => {
-        this.emit('peer-connected');
+      await listen<SessionPeer>("peer-connected", (e) => {
+        this.emit('peer-connected', e.payload);
+      }),
+      await listen<SessionPeer>("peer-disconnected", (e) => {
+        this.emit('peer-disconnected', e.payload);
       }),
-      await listen("peer-disconnected", () => {
-        this.emit('peer-disconnected');
+      await listen<SessionPeer>("peer-transport-changed", (e) => {
+        this.emit('peer-transport-changed', e.payload);
       }),
-      await listen<string>("join-requested", (e) => {
+      await listen<JoinRequest>("join-requested", (e) => {
         this.emit('join-requested', e.payload);
       }),
-      await listen<number[]>("join-accepted", async (e) => {
+      await listen<{ peer_id: string, reason: string }>("join-incompatible", (e) => {
+        this.emit('join-incompatible', e.payload);
+      }),
+      await listen<{ peer_id: string, reason: string | null }>("join-rejected", (e) => {
+        this.emit('join-rejected', e.payload);
+      }),
+      await listen<{ peer_id: string, reason: string }>("join-password-required", (e) => {
+        this.emit('join-password-required', e.payload);
+      }),
+      await listen<{ peer_id: string, reason: string }>("join-auth-failed", (e) => {
+        this.emit('join-auth-failed', e.payload);
+      }),
+      await listen<string>("join-timed-out", (e) => {
+        this.emit('join-timed-out', e.payload);
+      }),
+      await listen<JoinRequest>("join-auto-approved", (e) => {
+        this.emit('join-auto-approved', e.payload);
+      }),
+      await listen<string>("join-blocked", (e) => {
+        this.emit('join-blocked', e.payload);
+      }),
+      await listen<string>("join-request-expired", (e) => {
+        this.emit('join-request-expired', e.payload);
+      }),
+      await listen<string>("join-accepted", async (e) => {
         this.emit('join-accepted', e.payload);
       }),
+      await listen<TransferProgress>("transfer-progress", (e) => {
+        this.emit('transfer-progress', e.payload);
+      }),
+      await listen<TransferProgress>("transfer-complete", (e) => {
+        this.emit('transfer-complete', e.payload);
+      }),
+      await listen<TransferProgress>("transfer-failed", (e) => {
+        this.emit('transfer-failed', e.payload);
+      }),
+      await listen<string>("transfer-cancelled", (e) => {
+        this.emit('transfer-cancelled', e.payload);
+      }),
       await listen<string>("host-disconnected", (e) => {
         this.emit('host-disconnected', e.payload);
       }),
@@ -59,18 +172,93 @@ export class P2PService extends EventEmitter {
       }),
       await listen<{ path: string }>("sync-requested", async (e) => {
         this.emit('sync-requested', e.payload.path);
+      }),
+      await listen<DiscoveredPeer>("peer-discovered", (e) => {
+        this.emit('peer-discovered', e.payload);
+      }),
+      await listen<string>("peer-expired", (e) => {
+        this.emit('peer-expired', e.payload);
+      }),
+      await listen<{ updated: AwarenessEntry[], removed: number[] }>("awareness-changed", (e) => {
+        this.emit('awareness-changed', e.payload);
+      }),
+      await listen<{ peer_id: string, text: string, verified: boolean }>("chat-message", (e) => {
+        this.emit('chat-message', e.payload);
+      }),
+      await listen<SessionPeer>("peer-role-changed", (e) => {
+        this.emit('peer-role-changed', e.payload);
+      }),
+      await listen<SessionEnded>("session-ended", (e) => {
+        this.emit('session-ended', e.payload);
+      }),
+      await listen<{ peer_id: string, banned: boolean }>("peer-kicked", (e) => {
+        this.emit('peer-kicked', e.payload);
+      }),
+      await listen<SessionRole>("session-role-changed", (e) => {
+        this.emit('session-role-changed', e.payload);
+      }),
+      await listen<PermissionDenied>("permission-denied", (e) => {
+        this.emit('permission-denied', e.payload);
       })
     );
   }
 
   // --- Actions ---
 
-  async sendJoinRequest(peerId: string, remoteAddrs: string[]) {
-    await invoke("request_join", { peerId, remoteAddrs });
+  /** `projectPath` is our existing copy of the project, so the host only sends what changed. */
+  async sendJoinRequest(peerId: string, remoteAddrs: string[], profile: JoinProfile, projectPath: string | null = null, password: string | null = null) {
+    await invoke("request_join", { peerId, remoteAddrs, profile, password, projectPath });
+  }
+
+  /** Guests must prove they know `password` before their join request reaches us. */
+  async setSessionPassword(password: string | null) {
+    await invoke("set_session_password", { password });
+  }
+
+  async approveJoin(peerId: string, projectPath: string, role: SessionRole = 'editor') {
+    await invoke("approve_join", { peerId, projectPath, role });
+  }
+
+  /** Changes what a guest of our session may do. */
+  async setPeerRole(peerId: string, role: SessionRole) {
+    await invoke("set_peer_role", { peerId, role });
+  }
+
+  /** Removes a guest from our session; `reason` is shown to them. */
+  async kickPeer(peerId: string, reason: string | null = null) {
+    await invoke("kick_peer", { peerId, reason });
+  }
+
+  /** Like `kickPeer`, but also blocks the peer from joining again. */
+  async banPeer(peerId: string, reason: string | null = null) {
+    await invoke("ban_peer", { peerId, reason });
+  }
+
+  /** Our own role in the current session, or null outside of one. */
+  async getSessionRole() {
+    return invoke<SessionRole | null>("get_session_role");
+  }
+
+  async rejectJoin(peerId: string, reason: string | null = null) {
+    await invoke("reject_join", { peerId, reason });
+  }
+
+  async listTrustedPeers() {
+    return invoke<TrustedPeer[]>("list_trusted_peers");
+  }
+
+  /** Remembers `peerId` as trusted or blocked; `note` helps recognise it later. */
+  async setPeerTrust(peerId: string, level: TrustedPeer['level'], note: string | null = null) {
+    await invoke("set_peer_trust", { peerId, level, note });
   }
 
-  async approveJoin(peerId: string, projectPath: string) {
-    await invoke("approve_join", { peerId, projectPath });
+  async removePeerTrust(peerId: string) {
+    await invoke("remove_peer_trust", { peerId });
+  }
+
+  /** Aborts the project transfer with `peerId`; the other side gets `transfer-failed`. */
+  async cancelTransfer(peerId: string) {
+    await invoke("cancel_transfer", { peerId });
   }
 
   async requestFileSync(path: string) {
@@ -81,6 +269,36 @@ export class P2PService extends EventEmitter {
     await invoke("broadcast_update", { path, data });
   }
 
+  /** Fetches the backend's copy of a document, or only what is missing from `stateVector`. */
+  async getDocumentState(path: string, stateVector?: number[]) {
+    return invoke<number[] | null>("get_document_state", { path, stateVector: stateVector ?? null });
+  }
+
+  /** Publishes the local client's awareness state (cursor, selection, user); `null` clears it. */
+  async setAwarenessState(clientId: number, state: unknown | null) {
+    await invoke("set_awareness_state", { clientId, state: state === null ? null : JSON.stringify(state) });
+  }
+
+  async getAwarenessStates() {
+    return invoke<AwarenessEntry[]>("get_awareness_states");
+  }
+
+  async sendChatMessage(text: string) {
+    await invoke("send_chat_message", { text });
+  }
+
+  async listSessionPeers() {
+    return invoke<SessionPeer[]>("list_session_peers");
+  }
+
+  async listNearbyHosts() {
+    return invoke<DiscoveredPeer[]>("list_nearby_hosts");
+  }
+
+  async advertiseSession(projectName: string | null) {
+    await invoke("advertise_session", { projectName });
+  }
+
   async destroy() {
     this.unlistenFns.forEach(unlisten => unlisten());
     this.unlistenFns = [];
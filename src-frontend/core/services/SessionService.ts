@@ -1,4 +1,4 @@
-import { P2PService } from "./P2PService"; 
+import { P2PService, TransferProgress, JoinRequest, SessionEnded } from "./P2PService"; 
 import { AuthService } from "./AuthService"; 
 import { FileSystemService } from "./FileSystemService"; 
 import { CollabService } from "./CollabService"; 
@@ -19,6 +19,9 @@ export class SessionService {
     // Format: ${hostId}:${rootPath} 
     private activeSessionKey: string | null = null;
 
+    // Last join we sent, retried with a password if the host asks for one
+    private lastJoin: { hostId: string, addrs: string[], rootPath: string | null, message: string } | null = null;
+
     constructor(p2p: P2PService, auth: AuthService, fs: FileSystemService, collab: CollabService) { this.p2p = p2p; this.auth = auth; this.fs = fs; this.collab = collab; this.setupListeners(); }
 
     private setupListeners() {
@@ -29,19 +32,72 @@ export class SessionService {
             store.setConnectedPeers(Math.max(0, store.connectedPeers - 1));
         });
 
-        this.p2p.on('join-requested', (peerId: string) => {
-            useSessionStore.getState().setIncomingRequest(peerId);
+        this.p2p.on('join-requested', (request: JoinRequest) => {
+            useSessionStore.getState().setIncomingRequest(request);
+        });
+
+        this.p2p.on('join-incompatible', ({ peer_id, reason }: { peer_id: string, reason: string }) => {
+            useUIStore.getState().setWarningMsg(`Refused join from ${peer_id.slice(0, 8)}: ${reason}`);
+        });
+
+        this.p2p.on('join-request-expired', (peerId: string) => {
+            const store = useSessionStore.getState();
+            if (store.incomingRequest?.peer_id === peerId) store.setIncomingRequest(null);
+        });
+
+        this.p2p.on('join-rejected', ({ reason }: { peer_id: string, reason: string | null }) => {
+            this.activeSessionKey = null;
+            useSessionStore.getState().setStatus('error', reason ? `Host refused to let you join: ${reason}` : "Host refused to let you join");
+        });
+
+        this.p2p.on('join-password-required', async ({ peer_id, reason }: { peer_id: string, reason: string }) => {
+            const join = this.lastJoin;
+            if (!join || join.hostId !== peer_id) return;
+            const password = await useUIStore.getState().requestPassword(`🔒 ${reason}.\nEnter session password:`);
+            if (!password) {
+                this.activeSessionKey = null;
+                useSessionStore.getState().setStatus('error', "Join cancelled: session password required");
+                return;
+            }
+            useSessionStore.getState().setStatus('negotiating', `Joining ${peer_id.slice(0, 8)}...`);
+            this.requestJoin(join.hostId, join.addrs, join.rootPath, join.message, password)
+                .catch((e) => useSessionStore.getState().setStatus('error', `Join request failed: ${e}`));
+        });
+
+        this.p2p.on('join-auth-failed', ({ peer_id, reason }: { peer_id: string, reason: string }) => {
+            console.warn(`Refused join from ${peer_id}: ${reason}`);
+        });
+
+        this.p2p.on('join-timed-out', () => {
+            this.activeSessionKey = null;
+            useSessionStore.getState().setStatus('error', "The host did not answer the join request");
         });
 
-        this.p2p.on('join-accepted', (data: number[]) => {
+        this.p2p.on('join-accepted', () => {
             useSessionStore.getState().setStatus('syncing', "Joined! Syncing project...");
             this.setHostRole(false);
         });
 
+        this.p2p.on('transfer-progress', (p: TransferProgress) => {
+            if (p.direction !== 'receive') return;
+            useSessionStore.getState().setStatus('syncing', `Receiving project... ${p.files_done}/${p.files_total} files`);
+        });
+
+        this.p2p.on('transfer-failed', (p: TransferProgress) => {
+            if (p.direction !== 'receive') return;
+            useSessionStore.getState().setStatus('error', `Project transfer failed: ${p.error}`);
+        });
+
         this.p2p.on('host-disconnected', (id: string) => {
             this.handleHostLoss(id);
         });
 
+        // Removed by the host: there is nothing to take over
+        this.p2p.on('session-ended', ({ reason }: SessionEnded) => {
+            this.stopHeartbeatListener();
+            useSessionStore.getState().setStatus('error', `Removed from the session: ${reason}`);
+        });
+
         // Listen for heartbeat packets
         this.p2p.on('sync-packet', ({ path }: { path: string }) => {
             if (path === '.heartbeat') {
@@ -60,6 +116,7 @@ export class SessionService {
         } else {
             this.stopHeartbeatEmitter();
             this.startHeartbeatListener();
+            this.p2p.advertiseSession(null).catch(() => {});
         }
 
     }
@@ -213,11 +270,11 @@ export class SessionService {
                 store.setStatus('negotiating', `Joining ${meta.hostId.slice(0, 8)}...`);
 
                 try {
-                    await this.p2p.sendJoinRequest(meta.hostId, targetAddrs);
+                    await this.requestJoin(meta.hostId, targetAddrs, rootPath);
                 } catch (e) {
                     console.error("Failed to send join request", e);
                     this.activeSessionKey = null; // Reset on failure
-                    store.setStatus('error', "Join request failed");
+                    store.setStatus('error', `Join request failed: ${e}`);
                 }
                 return;
             }
@@ -229,6 +286,30 @@ export class SessionService {
 
     }
 
+    /** Asks `hostId` to let us join; a password prompt follows if the session needs one. */
+    public async requestJoin(hostId: string, addrs: string[], rootPath: string | null, message = "", password: string | null = null) {
+        // Only a copy of this host's project may be diffed against: the host would
+        // read every file of an unrelated folder as deleted
+        const baseline = rootPath && await this.isCopyOf(rootPath, hostId) ? rootPath : null;
+        this.lastJoin = { hostId, addrs, rootPath: baseline, message };
+        const profile = useProjectStore.getState().getJoinProfile(message);
+        await this.p2p.sendJoinRequest(hostId, addrs, profile, baseline, password);
+    }
+
+    /** Folder the project being received updates in place, or null if the user has to pick one. */
+    public joinBaseline(): string | null {
+        return this.lastJoin?.rootPath ?? null;
+    }
+
+    private async isCopyOf(rootPath: string, hostId: string) {
+        try {
+            const meta = JSON.parse(await this.fs.readFileString(`${rootPath}/${META_FILE}`));
+            return meta.hostId === hostId;
+        } catch {
+            return false;
+        }
+    }
+
     private async claimHost(rootPath: string, metaPath: string) {
         const store = useSessionStore.getState(); const myPeerId = this.p2p.getPeerId(); const myAddrs = this.p2p.getAddresses(); const key = this.auth.getKey();
 
@@ -268,6 +349,9 @@ export class SessionService {
         this.setHostRole(true);
         store.setStatus('connected', "Hosting Session");
 
+        const projectName = rootPath.split(/[\\/]/).filter(Boolean).pop() || rootPath;
+        await this.p2p.advertiseSession(projectName).catch((e) => console.warn("Failed to advertise session", e));
+
     }
 
     private async handleDecryption(token: string): Promise<boolean> {
@@ -1,4 +1,5 @@
 import { create } from "zustand";
+import type { JoinProfile } from "../services/P2PService";
 
 interface ProjectState {
   rootPath: string;
@@ -7,6 +8,10 @@ interface ProjectState {
   sshKeyPath: string;
   encryptionKey: string;
   detectedRemote: string;
+  displayName: string;
+  userColor: string;
+  /** Kept in memory only; the backend holds the copy it checks guests against. */
+  sessionPassword: string;
 
   setRootPath: (path: string) => void;
   setCurrentFilePath: (path: string | null) => void;
@@ -14,15 +19,22 @@ interface ProjectState {
   setSshKeyPath: (path: string) => void;
   setEncryptionKey: (key: string) => void;
   setDetectedRemote: (remote: string) => void;
+  setProfile: (displayName: string, userColor: string) => void;
+  setSessionPassword: (password: string) => void;
+  /** Profile sent to hosts we ask to join. */
+  getJoinProfile: (message?: string) => JoinProfile;
 }
 
-export const useProjectStore = create<ProjectState>((set) => ({
+export const useProjectStore = create<ProjectState>((set, get) => ({
   rootPath: "",
   currentFilePath: null,
   fileSystemRefresh: 0,
   sshKeyPath: localStorage.getItem("sshKeyPath") || "",
   encryptionKey: localStorage.getItem("encryptionKey") || "",
   detectedRemote: "",
+  displayName: localStorage.getItem("displayName") || "Guest",
+  userColor: localStorage.getItem("userColor") || "#89b4fa",
+  sessionPassword: "",
 
   setRootPath: (path) => set({ rootPath: path }),
   setCurrentFilePath: (path) => set({ currentFilePath: path }),
@@ -36,4 +48,16 @@ export const useProjectStore = create<ProjectState>((set) => ({
     set({ encryptionKey: key });
   },
   setDetectedRemote: (remote) => set({ detectedRemote: remote }),
+  setProfile: (displayName, userColor) => {
+    localStorage.setItem("displayName", displayName);
+    localStorage.setItem("userColor", userColor);
+    set({ displayName, userColor });
+  },
+  setSessionPassword: (password) => set({ sessionPassword: password }),
+  getJoinProfile: (message = "") => ({
+    display_name: get().displayName,
+    color: get().userColor,
+    avatar: null,
+    message,
+  }),
 }));
\ No newline at end of file
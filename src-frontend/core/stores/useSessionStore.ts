@@ -1,4 +1,5 @@
 import { create } from "zustand";
+import type { JoinRequest } from "../services/P2PService";
 
 export type SessionStatus = 'idle' | 'negotiating' | 'syncing' | 'connected' | 'error' | 'offline';
 
@@ -7,13 +8,13 @@ interface SessionState {
   statusMessage: string;
   isHost: boolean;
   connectedPeers: number;
-  incomingRequest: string | null;
+  incomingRequest: JoinRequest | null;
   deadHostId: string | null;
   
   setStatus: (status: SessionStatus, msg?: string) => void;
   setIsHost: (val: boolean) => void;
   setConnectedPeers: (count: number) => void;
-  setIncomingRequest: (id: string | null) => void;
+  setIncomingRequest: (request: JoinRequest | null) => void;
   setDeadHostId: (id: string | null) => void;
 }
 
@@ -28,6 +29,6 @@ export const useSessionStore = create<SessionState>((set) => ({
   setStatus: (status, msg) => set({ status, statusMessage: msg || status }),
   setIsHost: (val) => set({ isHost: val }),
   setConnectedPeers: (count) => set({ connectedPeers: count }),
-  setIncomingRequest: (id) => set({ incomingRequest: id }),
+  setIncomingRequest: (request) => set({ incomingRequest: request }),
   setDeadHostId: (id) => set({ deadHostId: id }),
 }));
\ No newline at end of file
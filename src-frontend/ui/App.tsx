@@ -5,6 +5,7 @@ import { invoke } from "@tauri-apps/api/core";
 // Services & Stores
 import { registry } from "../engine/registry/Registry";
 import { workspaceManager, p2pService } from "../core/services";
+import type { TransferProgress } from "../core/services/P2PService";
 import { useProjectStore } from "../core/stores/useProjectStore";
 import { useSessionStore } from "../core/stores/useSessionStore";
 import { useUIStore } from "../core/stores/useUIStore";
@@ -43,8 +44,8 @@ function App() {
   // --- 2. P2P Event Bindings ---
   // We need to bind the "project-received" event from Transport to our Project Handler
   useEffect(() => {
-      const unsub = p2pService.on('join-accepted', (data: number[]) => {
-          handleProjectReceived(data);
+      const unsub = p2pService.on('transfer-complete', (progress: TransferProgress) => {
+          if (progress.direction === 'receive') handleProjectReceived(progress.peer_id);
       });
       return () => unsub();
   }, [handleProjectReceived]);
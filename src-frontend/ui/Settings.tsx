@@ -1,20 +1,27 @@
 import React, { useState, useEffect } from "react";
 import { useProjectStore } from "../core/stores/useProjectStore";
 import { useUIStore } from "../core/stores/useUIStore";
+import { p2pService } from "../core/services";
 
 export const Settings: React.FC = () => {
   const { isSettingsOpen, setShowSettings } = useUIStore();
-  const { sshKeyPath, setSshKeyPath, encryptionKey, setEncryptionKey, detectedRemote } = useProjectStore();
+  const { sshKeyPath, setSshKeyPath, encryptionKey, setEncryptionKey, detectedRemote, displayName, userColor, setProfile, sessionPassword, setSessionPassword } = useProjectStore();
 
   const [localSshPath, setLocalSshPath] = useState(sshKeyPath);
   const [localEncKey, setLocalEncKey] = useState(encryptionKey);
+  const [localName, setLocalName] = useState(displayName);
+  const [localColor, setLocalColor] = useState(userColor);
+  const [localSessionPassword, setLocalSessionPassword] = useState(sessionPassword);
 
   useEffect(() => {
     if (isSettingsOpen) {
       setLocalSshPath(sshKeyPath);
       setLocalEncKey(encryptionKey);
+      setLocalName(displayName);
+      setLocalColor(userColor);
+      setLocalSessionPassword(sessionPassword);
     }
-  }, [isSettingsOpen, sshKeyPath, encryptionKey]);
+  }, [isSettingsOpen, sshKeyPath, encryptionKey, displayName, userColor, sessionPassword]);
 
   if (!isSettingsOpen) return null;
 
@@ -30,6 +37,9 @@ export const Settings: React.FC = () => {
   const handleSave = () => {
     setSshKeyPath(localSshPath);
     setEncryptionKey(localEncKey);
+    setProfile(localName.trim() || "Guest", localColor);
+    setSessionPassword(localSessionPassword);
+    p2pService.setSessionPassword(localSessionPassword || null).catch((e) => console.error("Failed to set session password", e));
     setShowSettings(false);
   };
 
@@ -38,6 +48,15 @@ export const Settings: React.FC = () => {
       <div className="settings-modal" onClick={(e) => e.stopPropagation()}>
         <h3>Settings</h3>
         
+        <div className="setting-group">
+          <label>Display Name</label>
+          <div className="row">
+            <input type="text" value={localName} maxLength={64} onChange={(e) => setLocalName(e.target.value)} placeholder="Guest"/>
+            <input type="color" value={localColor} onChange={(e) => setLocalColor(e.target.value)} style={{ width: '48px' }}/>
+          </div>
+          <small>Shown to the host when you ask to join a session.</small>
+        </div>
+
         <div className="setting-group">
           <label>SSH Private Key Path (Optional)</label>
           <input type="text" value={localSshPath} onChange={(e) => setLocalSshPath(e.target.value)} placeholder="/Users/username/.ssh/id_rsa"/>
@@ -53,6 +72,12 @@ export const Settings: React.FC = () => {
           <small>If set, your IP address in the project file will be encrypted.</small>
         </div>
 
+        <div className="setting-group">
+          <label>Session Password (Optional)</label>
+          <input type="password" value={localSessionPassword} onChange={(e) => setLocalSessionPassword(e.target.value)} placeholder="Anyone you approve can join"/>
+          <small>When hosting, guests must enter this password before you see their request. It is never sent over the network.</small>
+        </div>
+
         {detectedRemote && (
           <div className="setting-group">
              <label>Detected Remote Origin</label>
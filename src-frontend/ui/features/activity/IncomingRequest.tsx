@@ -1,23 +1,42 @@
-import React from "react";
+import React, { useEffect, useState } from "react";
+import type { JoinRequest } from "../../../core/services/P2PService";
 
 interface IncomingRequestProps {
-  peerId: string;
+  request: JoinRequest;
   onAccept: () => void;
   onReject: () => void;
 }
 
 export const IncomingRequest: React.FC<IncomingRequestProps> = ({
-  peerId,
+  request,
   onAccept,
   onReject,
 }) => {
+  const { peer_id, profile, app_version } = request;
+  const [avatarUrl, setAvatarUrl] = useState<string | null>(null);
+
+  useEffect(() => {
+    if (!profile.avatar) return;
+    const url = URL.createObjectURL(new Blob([new Uint8Array(profile.avatar)]));
+    setAvatarUrl(url);
+    return () => { URL.revokeObjectURL(url); setAvatarUrl(null); };
+  }, [profile.avatar]);
+
   return (
-    <div className="request-card">
-      <p><strong>{peerId.slice(0, 8)}...</strong> wants to join.</p>
+    <div className="request-card" style={{ borderLeft: `4px solid ${profile.color}` }}>
+      <div className="row">
+        {avatarUrl && <img src={avatarUrl} alt="" width={32} height={32} style={{ borderRadius: '50%' }} />}
+        <p>
+          <strong style={{ color: profile.color }}>{profile.display_name}</strong> wants to join.
+          <br />
+          <small title={peer_id}>{peer_id.slice(0, 8)}... · my-editor {app_version}</small>
+        </p>
+      </div>
+      {profile.message && <blockquote>{profile.message}</blockquote>}
       <div className="actions">
         <button onClick={onAccept} className="btn-accept">Accept</button>
         <button onClick={onReject} className="btn-reject">Reject</button>
       </div>
     </div>
   );
-};
\ No newline at end of file
+};
@@ -1,16 +1,17 @@
 import { useCallback } from "react";
-import { p2pService } from "../../../core/services";
+import { p2pService, sessionService } from "../../../core/services";
 import { useSessionStore } from "../../../core/stores/useSessionStore";
+import { useProjectStore } from "../../../core/stores/useProjectStore";
 
 export function useP2P() {
   const {
     incomingRequest, setIncomingRequest, setStatus
   } = useSessionStore();
 
-  const sendJoinRequest = useCallback(async (peerId: string, remoteAddrs: string[] = []) => {
+  const sendJoinRequest = useCallback(async (peerId: string, remoteAddrs: string[] = [], message = "") => {
     // Ideally this goes through sessionService.negotiateHost, but for manual override:
     setStatus('negotiating', `Manually joining ${peerId.slice(0, 8)}...`);
-    await p2pService.sendJoinRequest(peerId, remoteAddrs);
+    await sessionService.requestJoin(peerId, remoteAddrs, useProjectStore.getState().rootPath || null, message);
   }, [setStatus]);
 
   const acceptRequest = useCallback(async (currentPath: string) => {
@@ -20,15 +21,19 @@ export function useP2P() {
         return;
     }
     try {
-      await p2pService.approveJoin(incomingRequest, currentPath);
+      await p2pService.approveJoin(incomingRequest.peer_id, currentPath);
       setIncomingRequest(null);
-      setStatus('connected', `Accepted ${incomingRequest.slice(0, 8)}. Sending folder...`);
+      setStatus('connected', `Accepted ${incomingRequest.profile.display_name}. Sending folder...`);
     } catch (e) {
       setStatus('error', `Error accepting: ${e}`);
     }
   }, [incomingRequest, setIncomingRequest, setStatus]);
 
-  const rejectRequest = useCallback(() => setIncomingRequest(null), [setIncomingRequest]);
+  const rejectRequest = useCallback(async (reason: string | null = null) => {
+    if (!incomingRequest) return;
+    setIncomingRequest(null);
+    await p2pService.rejectJoin(incomingRequest.peer_id, reason).catch((e) => console.warn("Failed to reject join", e));
+  }, [incomingRequest, setIncomingRequest]);
 
   const requestSync = useCallback(async (path: string) => {
     try {
@@ -68,7 +68,7 @@ export const Sidebar: React.FC = () => {
           <p className="status-text">{statusMessage}</p>
           {incomingRequest && (
             <IncomingRequest 
-              peerId={incomingRequest} 
+              request={incomingRequest} 
               onAccept={handleAcceptRequest} 
               onReject={() => rejectRequest()} 
             />
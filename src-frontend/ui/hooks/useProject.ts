@@ -1,8 +1,7 @@
 import { useRef, useCallback, useEffect } from "react";
-import { open } from "@tauri-apps/plugin-dialog";
 import { workspaceManager } from "../../core/services";
 import { pluginLoader } from "../../engine/PluginLoader";
-import { fsService } from "../../core/services";
+import { fsService, sessionService } from "../../core/services";
 import { useProjectStore } from "../../core/stores/useProjectStore";
 import { useUIStore } from "../../core/stores/useUIStore";
 import { useSessionStore } from "../../core/stores/useSessionStore";
@@ -54,9 +53,9 @@ export function useProject() {
     }
 
     try {
-      const selected = await open({ directory: true, multiple: false, title: "Open Project Folder" });
+      const selected = await fsService.openWorkspace("Open Project Folder");
 
-      if (selected && typeof selected === 'string') {
+      if (selected) {
         try {
             const content = await fsService.readFileString(`${selected}/${FILES.METADATA}`);
             const json = JSON.parse(content);
@@ -87,22 +86,25 @@ export function useProject() {
     }
   };
 
-  const handleProjectReceived = useCallback(async (data: number[]) => {
+  const handleProjectReceived = useCallback(async (transferId: string) => {
     let destPath: string | null = null;
     let silent = false;
 
-    if (rootPath) {
+    // Update the open folder only if it is what we told the host we have
+    if (rootPath && sessionService.joinBaseline() === rootPath) {
         destPath = rootPath;
         silent = true; 
         isAutoJoining.current = false; 
     } else {
-        const selected = await open({ directory: true, multiple: false, title: "Select Destination" });
-        if (selected && typeof selected === 'string') destPath = selected;
+        destPath = await fsService.openWorkspace("Select Destination");
     }
 
     if (destPath) {
       try {
-        await fsService.saveIncomingProject(destPath, data);
+        const report = await fsService.saveIncomingProject(destPath, transferId);
+        if (report.skipped.length > 0) {
+            setWarningMsg(`Skipped ${report.skipped.length} unsafe entries from the host:\n- ${report.skipped.map(e => `${e.path} (${e.reason})`).join('\n- ')}`);
+        }
         setRootPath(destPath);
         triggerFileSystemRefresh();
         setDetectedRemote("");
@@ -0,0 +1,332 @@
+use clap::{Parser, Subcommand};
+use my_editor_core::config::NetworkConfig;
+use my_editor_core::merkle::ProjectTree;
+use my_editor_core::peers::SessionRole;
+use my_editor_core::trust::{TrustLevel, TrustList};
+use my_editor_core::protocol::{JoinProfile, Reply, SwarmCommand};
+use my_editor_core::{files, transfer, EventSink, Events, PeerState};
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc;
+
+/// Runs a my-editor peer without the GUI.
+///
+/// Session events are printed to stdout as JSON lines
+/// (`{"event": "...", "payload": ...}`) and nothing else; logs go to stderr.
+/// While running, stdin accepts `approve <peer_id> [editor|viewer]`,
+/// `reject <peer_id> [reason]`, `role <peer_id> <editor|viewer>`,
+/// `kick <peer_id> [reason]`, `ban <peer_id> [reason]`,
+/// `trust <peer_id> [note]`, `block <peer_id> [note]`, `forget <peer_id>`,
+/// `cancel <peer_id>`, `chat <text>` and `quit`. Trusted peers join without
+/// `approve`; blocked and banned ones are refused.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Directory holding the peer identity and network config
+    #[arg(short, long, default_value = ".my-editor-cli")]
+    data_dir: PathBuf,
+    /// Listen address (repeatable), replaces the configured ones
+    #[arg(short, long)]
+    listen: Vec<String>,
+    /// Relay multiaddr ending in /p2p/<id> (repeatable), replaces the configured ones
+    #[arg(short, long)]
+    relay: Vec<String>,
+    /// Don't dial any relay
+    #[arg(long)]
+    no_relay: bool,
+    /// Session password: required from guests when hosting, proven when joining
+    #[arg(long, env = "MY_EDITOR_SESSION_PASSWORD", hide_env_values = true)]
+    password: Option<String>,
+    #[command(subcommand)]
+    mode: Mode,
+}
+
+#[derive(Subcommand, Debug)]
+enum Mode {
+    /// Host a project directory
+    Host {
+        dir: PathBuf,
+        /// Accept every join request without waiting for `approve`
+        #[arg(long)]
+        approve_all: bool,
+        /// Role granted to approved guests: `editor` or `viewer`
+        #[arg(long, default_value = "editor")]
+        role: SessionRole,
+        /// Open a gossipsub topic for the session
+        #[arg(long)]
+        gossipsub: bool,
+    },
+    /// Join a host and mirror its project into a directory
+    Join {
+        peer_id: String,
+        /// Known address of the host (repeatable)
+        #[arg(short, long)]
+        addr: Vec<String>,
+        dir: PathBuf,
+        /// Name shown to the host
+        #[arg(long, default_value = "Guest")]
+        name: String,
+        /// Hex color such as `#89b4fa`
+        #[arg(long, default_value = "#89b4fa")]
+        color: String,
+        /// Image file sent as the avatar
+        #[arg(long)]
+        avatar: Option<PathBuf>,
+        /// Note to the host
+        #[arg(short, long, default_value = "")]
+        message: String,
+    },
+}
+
+/// Hands core events to the main loop.
+struct ChannelEvents(mpsc::UnboundedSender<(String, Value)>);
+
+impl EventSink for ChannelEvents {
+    fn emit(&self, event: &str, payload: Value) {
+        let _ = self.0.send((event.to_string(), payload));
+    }
+}
+
+async fn dispatch<T>(sender: &mpsc::Sender<SwarmCommand>, command: impl FnOnce(Reply<T>) -> SwarmCommand) -> Result<T, String> {
+    let (reply, outcome) = tokio::sync::oneshot::channel();
+    sender.send(command(reply)).await.map_err(|e| e.to_string())?;
+    outcome.await.map_err(|_| "Network task stopped before replying".to_string())?
+}
+
+fn write_mirrored(root: &Path, path: &str, data: &[u8]) -> Result<(), String> {
+    files::write_file(&root.join(path), data)
+}
+
+/// Writes the merged state of a synced document (or the raw bytes of an
+/// asset) under `root`, in the same format the editor saves.
+fn mirror_sync(root: &Path, state: &PeerState, payload: &Value) -> Result<(), String> {
+    let path = payload["path"].as_str().ok_or("Sync event without path")?;
+    if path == ".heartbeat" {
+        return Ok(());
+    }
+    let merged = state.documents.lock().unwrap_or_else(|e| e.into_inner()).diff(path, &[])?;
+    match merged {
+        Some(content) => write_mirrored(root, path, &content),
+        None => write_mirrored(root, path, &bytes(&payload["data"])),
+    }
+}
+
+fn bytes(value: &Value) -> Vec<u8> {
+    serde_json::from_value(value.clone()).unwrap_or_default()
+}
+
+fn project_name(dir: &Path) -> String {
+    dir.canonicalize()
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_default()
+}
+
+/// Changes the trust list and persists it next to the network config.
+fn update_trust(state: &PeerState, data_dir: &Path, change: impl FnOnce(&mut TrustList) -> Result<(), String>) -> Result<(), String> {
+    let mut trust = state.trust.lock().unwrap_or_else(|e| e.into_inner());
+    let mut updated = trust.clone();
+    change(&mut updated)?;
+    updated.save(data_dir)?;
+    *trust = updated;
+    Ok(())
+}
+
+async fn approve(sender: &mpsc::Sender<SwarmCommand>, dir: &Path, peer_id: String, role: SessionRole) -> Result<(), String> {
+    let (root, project) = (dir.to_path_buf(), project_name(dir));
+    dispatch(sender, |reply| SwarmCommand::AcceptJoin { peer_id, root, project, role, reply }).await
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let mut config = NetworkConfig::load(&args.data_dir);
+    if !args.listen.is_empty() {
+        config.listen_addrs = args.listen.clone();
+    }
+    if args.no_relay {
+        config.relays.clear();
+    } else if !args.relay.is_empty() {
+        config.relays = args.relay.iter().enumerate().map(|(i, address)| my_editor_core::config::RelayConfig {
+            address: address.clone(),
+            priority: i as u32,
+            enabled: true,
+        }).collect();
+    }
+    if let Mode::Host { gossipsub, .. } = &args.mode {
+        config.gossipsub = *gossipsub;
+    }
+    config.validate()?;
+    config.save(&args.data_dir)?;
+
+    let state = PeerState::new();
+    let (tx, rx) = mpsc::channel::<SwarmCommand>(32);
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+
+    let node_state = state.clone();
+    let data_dir = args.data_dir.clone();
+    let node = tokio::spawn(async move {
+        if let Err(e) = my_editor_core::start_p2p_node(Events::new(ChannelEvents(event_tx)), data_dir, node_state, rx).await {
+            eprintln!("P2P Network Error: {e}");
+        }
+    });
+
+    let root = match &args.mode {
+        Mode::Host { dir, .. } => {
+            fs::create_dir_all(dir)?;
+            *state.advertised_session.lock().unwrap_or_else(|e| e.into_inner()) = Some(project_name(dir));
+            *state.session_password.lock().unwrap_or_else(|e| e.into_inner()) = args.password.clone();
+            state.workspace.lock().unwrap_or_else(|e| e.into_inner()).open(&fs::canonicalize(dir)?)?;
+            dir.clone()
+        }
+        Mode::Join { peer_id, addr, dir, name, color, avatar, message } => {
+            let profile = JoinProfile {
+                display_name: name.clone(),
+                color: color.clone(),
+                avatar: avatar.as_ref().map(fs::read).transpose()?,
+                message: message.clone(),
+            };
+            profile.validate()?;
+            fs::create_dir_all(dir)?;
+            // Announce what we already mirrored so the host only sends changes
+            let tree = ProjectTree::build(dir)?;
+            let (peer_id, remote_addrs, password, sender) = (peer_id.clone(), addr.clone(), args.password.clone(), tx.clone());
+            tokio::spawn(async move {
+                if let Err(e) = dispatch(&sender, |reply| SwarmCommand::Join { peer_id, remote_addrs, profile, password, tree: Some(tree), reply }).await {
+                    eprintln!("Join request failed: {}", e);
+                }
+            });
+            dir.clone()
+        }
+    };
+    let approve_all = matches!(args.mode, Mode::Host { approve_all: true, .. });
+    let default_role = match args.mode {
+        Mode::Host { role, .. } => role,
+        Mode::Join { .. } => SessionRole::default(),
+    };
+
+    let mut stdin = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdin_open = true;
+
+    loop {
+        tokio::select! {
+            Some((event, payload)) = event_rx.recv() => {
+                println!("{}", json!({ "event": event, "payload": payload }));
+
+                let handled = match event.as_str() {
+                    "join-requested" if approve_all => match payload["peer_id"].as_str() {
+                        Some(peer) => approve(&tx, &root, peer.to_string(), default_role).await,
+                        None => Ok(()),
+                    },
+                    "transfer-complete" if payload["direction"] == "receive" => match payload["peer_id"].as_str() {
+                        Some(host) => transfer::staged_project(&args.data_dir, host)
+                            .and_then(|staged| files::save_incoming_project(&root, &staged))
+                            .map(|report| {
+                                for entry in report.skipped {
+                                    eprintln!("Skipped '{}' from the received project: {:?}", entry.path, entry.reason);
+                                }
+                            }),
+                        None => Ok(()),
+                    },
+                    "p2p-sync" => mirror_sync(&root, &state, &payload),
+                    "p2p-file-content" => match payload["path"].as_str() {
+                        Some(path) => write_mirrored(&root, path, &bytes(&payload["data"])),
+                        None => Ok(()),
+                    },
+                    "sync-requested" => match payload["path"].as_str() {
+                        Some(path) => match files::read_file(&root.join(path)) {
+                            Ok(data) => {
+                                let path = path.to_string();
+                                dispatch(&tx, |reply| SwarmCommand::Sync { path, data, reply }).await
+                            }
+                            // Nothing on disk: the document store answers on its own
+                            Err(_) => Ok(()),
+                        },
+                        None => Ok(()),
+                    },
+                    "host-disconnected" | "session-ended" if matches!(args.mode, Mode::Join { .. }) => break,
+                    _ => Ok(()),
+                };
+                if let Err(e) = handled {
+                    eprintln!("Failed to handle '{}': {}", event, e);
+                }
+            }
+
+            line = stdin.next_line(), if stdin_open => {
+                let line = match line {
+                    Ok(Some(line)) => line,
+                    _ => {
+                        stdin_open = false;
+                        continue;
+                    }
+                };
+                let (command, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+                let outcome = match command {
+                    "approve" => {
+                        let (peer_id, role) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+                        let role = match role.trim() {
+                            "" => Ok(default_role),
+                            role => role.parse(),
+                        };
+                        match role {
+                            Ok(role) => approve(&tx, &root, peer_id.to_string(), role).await,
+                            Err(e) => Err(e),
+                        }
+                    }
+                    "role" => {
+                        let (peer_id, role) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+                        match role.trim().parse() {
+                            Ok(role) => {
+                                let peer_id = peer_id.to_string();
+                                dispatch(&tx, |reply| SwarmCommand::SetPeerRole { peer_id, role, reply }).await
+                            }
+                            Err(e) => Err(e),
+                        }
+                    }
+                    "reject" => {
+                        let (peer_id, reason) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+                        let (peer_id, reason) = (peer_id.to_string(), Some(reason.to_string()));
+                        dispatch(&tx, |reply| SwarmCommand::RejectJoin { peer_id, reason, reply }).await
+                    }
+                    "kick" | "ban" => {
+                        let (peer_id, reason) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+                        let (peer_id, reason, ban) = (peer_id.to_string(), Some(reason.to_string()), command == "ban");
+                        dispatch(&tx, |reply| SwarmCommand::Kick { peer_id, reason, ban, reply }).await
+                    }
+                    "trust" | "block" => {
+                        let (peer_id, note) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+                        let level = if command == "trust" { TrustLevel::Trusted } else { TrustLevel::Blocked };
+                        update_trust(&state, &args.data_dir, |trust| trust.set(peer_id, level, note))
+                    }
+                    "forget" => update_trust(&state, &args.data_dir, |trust| match trust.remove(rest.trim()) {
+                        true => Ok(()),
+                        false => Err(format!("{} is not on the trust list", rest.trim())),
+                    }),
+                    "cancel" => {
+                        let peer_id = rest.trim().to_string();
+                        dispatch(&tx, |reply| SwarmCommand::CancelTransfer { peer_id, reply }).await
+                    }
+                    "chat" => {
+                        let text = rest.to_string();
+                        dispatch(&tx, |reply| SwarmCommand::Chat { text, reply }).await
+                    }
+                    "quit" => break,
+                    "" => Ok(()),
+                    other => Err(format!("Unknown command '{}'", other)),
+                };
+                if let Err(e) = outcome {
+                    eprintln!("{}", e);
+                }
+            }
+
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    node.abort();
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use hmac::{Hmac, Mac};
+use libp2p::PeerId;
+use sha2::Sha256;
+use spake2::{Ed25519Group, Identity, Password, Spake2};
+
+/// Failed password exchanges a peer gets before the host stops answering it.
+pub const MAX_PASSWORD_ATTEMPTS: u32 = 5;
+/// Unconfirmed exchanges from all peers together before further ones have to
+/// wait. Peer ids cost nothing, so the per-peer limit alone does not slow
+/// down guessing.
+pub const FREE_SESSION_GUESSES: u32 = 10;
+/// Wait after the first guess beyond `FREE_SESSION_GUESSES`, doubling with
+/// every further one up to `MAX_GUESS_BACKOFF`.
+pub const GUESS_BACKOFF: Duration = Duration::from_secs(1);
+pub const MAX_GUESS_BACKOFF: Duration = Duration::from_secs(60);
+/// Quiet time after which unconfirmed guesses stop counting towards the backoff.
+pub const GUESS_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+const HOST_CONFIRMATION: &[u8] = b"my-editor/session/host-confirmation";
+const GUEST_CONFIRMATION: &[u8] = b"my-editor/session/guest-confirmation";
+
+/// Which end of the join handshake a confirmation tag comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Host,
+    Guest,
+}
+
+/// Our half of a SPAKE2 run on the session password. Both peers derive the same
+/// key only if they used the same password; neither the password nor anything
+/// that allows testing guesses offline goes over the wire.
+pub struct PasswordExchange(Spake2<Ed25519Group>);
+
+impl PasswordExchange {
+    /// Starts an exchange between `host` and `guest`, returning the message to
+    /// send to the other side. Binding both peer ids means the messages are
+    /// useless to anyone relaying them between other peers.
+    pub fn start(password: &str, host: &PeerId, guest: &PeerId) -> (Self, Vec<u8>) {
+        let identity = Identity::new(format!("my-editor/session/{}/{}", host, guest).as_bytes());
+        let (state, message) = Spake2::<Ed25519Group>::start_symmetric(&Password::new(password.as_bytes()), &identity);
+        (Self(state), message)
+    }
+
+    pub fn finish(self, remote: &[u8]) -> Result<SessionKey, String> {
+        self.0.finish(remote).map(SessionKey).map_err(|e| format!("Invalid password exchange message: {:?}", e))
+    }
+}
+
+/// Key shared by a host and a guest that proved they know the session password.
+pub struct SessionKey(Vec<u8>);
+
+impl SessionKey {
+    /// Tag proving to the other side that `side` derived this key.
+    pub fn confirmation(&self, side: Side) -> Vec<u8> {
+        self.mac(side).finalize().into_bytes().to_vec()
+    }
+
+    /// Checks the other side's tag in constant time.
+    pub fn verify(&self, side: Side, tag: &[u8]) -> bool {
+        self.mac(side).verify_slice(tag).is_ok()
+    }
+
+    fn mac(&self, side: Side) -> Hmac<Sha256> {
+        let label = match side {
+            Side::Host => HOST_CONFIRMATION,
+            Side::Guest => GUEST_CONFIRMATION,
+        };
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC accepts any key length");
+        mac.update(label);
+        mac
+    }
+}
+
+/// Rations password exchanges, each of which is one online guess: a fixed
+/// number per peer, and across all peers an exponential backoff once more
+/// than `FREE_SESSION_GUESSES` are unconfirmed. A guess only stops counting
+/// once the join it was for proved the password.
+#[derive(Default)]
+pub struct GuessLimiter {
+    per_peer: HashMap<PeerId, u32>,
+    unconfirmed: u32,
+    last_guess: Option<Instant>,
+    next_guess: Option<Instant>,
+}
+
+impl GuessLimiter {
+    /// Whether `peer` may not start another exchange right now: it used up its
+    /// own attempts, or everyone's guesses came too fast.
+    pub fn is_locked(&mut self, peer: &PeerId, now: Instant) -> bool {
+        if self.last_guess.is_some_and(|t| now.duration_since(t) >= GUESS_WINDOW) {
+            self.unconfirmed = 0;
+            self.last_guess = None;
+            self.next_guess = None;
+        }
+        self.per_peer.get(peer).is_some_and(|n| *n >= MAX_PASSWORD_ATTEMPTS) || self.next_guess.is_some_and(|t| now < t)
+    }
+
+    /// Counts a guess by `peer`, unless it is locked out. Returns whether the
+    /// exchange may go ahead.
+    pub fn try_guess(&mut self, peer: PeerId, now: Instant) -> bool {
+        if self.is_locked(&peer, now) {
+            return false;
+        }
+        *self.per_peer.entry(peer).or_default() += 1;
+        self.unconfirmed += 1;
+        self.last_guess = Some(now);
+        if let Some(excess) = self.unconfirmed.checked_sub(FREE_SESSION_GUESSES + 1) {
+            let backoff = GUESS_BACKOFF.saturating_mul(1 << excess.min(16)).min(MAX_GUESS_BACKOFF);
+            self.next_guess = Some(now + backoff);
+        }
+        true
+    }
+
+    /// Forgets the guesses of `peer` after it proved the password; those of
+    /// other peers keep counting.
+    pub fn succeeded(&mut self, peer: &PeerId) {
+        let guesses = self.per_peer.remove(peer).unwrap_or(0);
+        self.unconfirmed = self.unconfirmed.saturating_sub(guesses);
+        if self.unconfirmed <= FREE_SESSION_GUESSES {
+            self.next_guess = None;
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use libp2p::PeerId;
+use crate::protocol::AwarenessEntry;
+
+/// Remote states not refreshed within this window are dropped, matching the
+/// `outdatedTimeout` of y-protocols' awareness.
+pub const OUTDATED_TIMEOUT: Duration = Duration::from_secs(30);
+
+struct Record {
+    /// Peer we received this client from, `None` for our own clients.
+    origin: Option<PeerId>,
+    clock: u32,
+    state: Option<String>,
+    updated_at: Instant,
+}
+
+impl Record {
+    fn entry(&self, client_id: u64) -> AwarenessEntry {
+        AwarenessEntry { client_id, clock: self.clock, state: self.state.clone() }
+    }
+}
+
+/// Ephemeral per-client state (cursors, selections, user info) of everyone in
+/// the session.
+#[derive(Default)]
+pub struct AwarenessStore {
+    records: HashMap<u64, Record>,
+}
+
+impl AwarenessStore {
+    /// Sets the state of one of our own clients, bumping its clock.
+    pub fn set_local(&mut self, client_id: u64, state: Option<String>) -> AwarenessEntry {
+        let clock = self.records.get(&client_id).map_or(0, |r| r.clock + 1);
+        let record = Record { origin: None, clock, state, updated_at: Instant::now() };
+        let entry = record.entry(client_id);
+        self.records.insert(client_id, record);
+        entry
+    }
+
+    /// Applies entries received from `origin`, returning the ones that were
+    /// newer than what we had. A peer may only update the clients it
+    /// announced first; entries for anyone else's clients are dropped.
+    pub fn apply(&mut self, origin: PeerId, entries: Vec<AwarenessEntry>) -> Vec<AwarenessEntry> {
+        self.merge(origin, false, entries)
+    }
+
+    /// Like `apply`, for entries from the host of the session we joined: it
+    /// relays every member's clients and their removal, so it may update any
+    /// remote client.
+    pub fn apply_from_host(&mut self, host: PeerId, entries: Vec<AwarenessEntry>) -> Vec<AwarenessEntry> {
+        self.merge(host, true, entries)
+    }
+
+    fn merge(&mut self, origin: PeerId, relays: bool, entries: Vec<AwarenessEntry>) -> Vec<AwarenessEntry> {
+        let now = Instant::now();
+        let mut applied = Vec::new();
+        for entry in entries {
+            let newer = match self.records.get(&entry.client_id) {
+                None => true,
+                Some(current) => {
+                    // Never let a peer overwrite our own clients, or another peer's
+                    let owned = current.origin.is_some_and(|owner| relays || owner == origin);
+                    owned
+                        && (entry.clock > current.clock
+                            || (entry.clock == current.clock && entry.state.is_none() && current.state.is_some()))
+                }
+            };
+            if !newer {
+                continue;
+            }
+            self.records.insert(entry.client_id, Record {
+                origin: Some(origin),
+                clock: entry.clock,
+                state: entry.state.clone(),
+                updated_at: now,
+            });
+            applied.push(entry);
+        }
+        applied
+    }
+
+    /// Drops remote clients that have not been refreshed in time. Returns the
+    /// ids of those that still had a state.
+    pub fn expire(&mut self, now: Instant) -> Vec<u64> {
+        let mut removed = Vec::new();
+        self.records.retain(|client_id, record| {
+            let stale = record.origin.is_some() && now.duration_since(record.updated_at) >= OUTDATED_TIMEOUT;
+            if stale && record.state.is_some() {
+                removed.push(*client_id);
+            }
+            !stale
+        });
+        removed
+    }
+
+    /// Bumps the clock of our own clients that are halfway to expiring, so
+    /// peers keep them alive. Returns the entries to send.
+    pub fn renew_local(&mut self, now: Instant) -> Vec<AwarenessEntry> {
+        let mut renewed = Vec::new();
+        for (client_id, record) in self.records.iter_mut() {
+            if record.origin.is_none() && record.state.is_some() && now.duration_since(record.updated_at) >= OUTDATED_TIMEOUT / 2 {
+                record.clock += 1;
+                record.updated_at = now;
+                renewed.push(record.entry(*client_id));
+            }
+        }
+        renewed
+    }
+
+    /// Removes every client that came from `peer`, e.g. when it disconnects.
+    /// Returns removal entries (state `None`, clock bumped) to pass on to the
+    /// rest of the session.
+    pub fn remove_peer(&mut self, peer: &PeerId) -> Vec<AwarenessEntry> {
+        let mut removed = Vec::new();
+        self.records.retain(|client_id, record| {
+            if record.origin.as_ref() == Some(peer) {
+                if record.state.is_some() {
+                    removed.push(AwarenessEntry { client_id: *client_id, clock: record.clock + 1, state: None });
+                }
+                false
+            } else {
+                true
+            }
+        });
+        removed
+    }
+
+    /// Current states of every known client, including our own.
+    pub fn states(&self) -> Vec<AwarenessEntry> {
+        self.records
+            .iter()
+            .filter(|(_, r)| r.state.is_some())
+            .map(|(id, r)| r.entry(*id))
+            .collect()
+    }
+}
@@ -0,0 +1,145 @@
+use std::io;
+use async_trait::async_trait;
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use libp2p::request_response;
+use libp2p::StreamProtocol;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::protocol::{AppRequest, AppResponse};
+
+/// Original protocol: plain JSON, as spoken by older peers. Kept so they can
+/// still be answered, e.g. with the reason their join is refused; sharing a
+/// session takes `MIN_PROTOCOL_VERSION` whatever the codec.
+pub const JSON_PROTOCOL: StreamProtocol = StreamProtocol::new("/collab/1.0.0");
+/// CBOR with byte strings for update and file payloads, optionally zstd-compressed.
+pub const BINARY_PROTOCOL: StreamProtocol = StreamProtocol::new("/collab/2.0.0");
+
+/// Same limits as libp2p's JSON codec, so old and new peers agree.
+const JSON_REQUEST_LIMIT: u64 = 1024 * 1024;
+const JSON_RESPONSE_LIMIT: u64 = 10 * 1024 * 1024;
+/// Bound on binary messages, before and after decompression.
+const BINARY_LIMIT: u64 = 16 * 1024 * 1024;
+/// Smaller payloads are not worth compressing.
+const COMPRESSION_THRESHOLD: usize = 1024;
+const ZSTD_LEVEL: i32 = 3;
+
+/// First byte of a binary message.
+const FLAG_PLAIN: u8 = 0;
+const FLAG_ZSTD: u8 = 1;
+
+/// Encodes a binary message: a flag byte, then the CBOR body, compressed
+/// when that pays off and `compress` is set.
+pub fn encode_binary<T: Serialize>(value: &T, compress: bool) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    ciborium::into_writer(value, &mut body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    if compress && body.len() >= COMPRESSION_THRESHOLD {
+        let compressed = zstd::bulk::compress(&body, ZSTD_LEVEL)?;
+        if compressed.len() < body.len() {
+            let mut message = Vec::with_capacity(compressed.len() + 1);
+            message.push(FLAG_ZSTD);
+            message.extend_from_slice(&compressed);
+            return Ok(message);
+        }
+    }
+    let mut message = Vec::with_capacity(body.len() + 1);
+    message.push(FLAG_PLAIN);
+    message.extend_from_slice(&body);
+    Ok(message)
+}
+
+pub fn decode_binary<T: DeserializeOwned>(message: &[u8]) -> io::Result<T> {
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+    let (flag, body) = message.split_first().ok_or_else(|| invalid("Empty message".into()))?;
+    match *flag {
+        FLAG_PLAIN => ciborium::from_reader(body).map_err(|e| invalid(e.to_string())),
+        FLAG_ZSTD => {
+            // Stream so small messages stay small; one byte past the limit
+            // tells an oversized body apart from one that just fits
+            let mut decompressed = Vec::new();
+            let decoder = zstd::stream::read::Decoder::with_buffer(body)?;
+            io::Read::read_to_end(&mut io::Read::take(decoder, BINARY_LIMIT + 1), &mut decompressed)?;
+            if decompressed.len() as u64 > BINARY_LIMIT {
+                return Err(invalid(format!("Decompressed message exceeds {} bytes", BINARY_LIMIT)));
+            }
+            ciborium::from_reader(decompressed.as_slice()).map_err(|e| invalid(e.to_string()))
+        }
+        other => Err(invalid(format!("Unknown message flag {}", other))),
+    }
+}
+
+/// Request-response codec for both `/collab` versions; the negotiated
+/// protocol picks the encoding.
+#[derive(Debug, Clone)]
+pub struct CollabCodec {
+    /// Compress large outgoing binary messages.
+    compression: bool,
+}
+
+impl CollabCodec {
+    pub fn new(compression: bool) -> Self {
+        Self { compression }
+    }
+
+    async fn read<T, M>(protocol: &StreamProtocol, io: &mut T, json_limit: u64) -> io::Result<M>
+    where
+        T: AsyncRead + Unpin + Send,
+        M: DeserializeOwned,
+    {
+        let mut message = Vec::new();
+        if *protocol == BINARY_PROTOCOL {
+            io.take(BINARY_LIMIT).read_to_end(&mut message).await?;
+            decode_binary(&message)
+        } else {
+            io.take(json_limit).read_to_end(&mut message).await?;
+            Ok(serde_json::from_slice(&message)?)
+        }
+    }
+
+    async fn write<T, M>(&self, protocol: &StreamProtocol, io: &mut T, value: M) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+        M: Serialize,
+    {
+        let message = if *protocol == BINARY_PROTOCOL {
+            encode_binary(&value, self.compression)?
+        } else {
+            serde_json::to_vec(&value)?
+        };
+        io.write_all(&message).await
+    }
+}
+
+#[async_trait]
+impl request_response::Codec for CollabCodec {
+    type Protocol = StreamProtocol;
+    type Request = AppRequest;
+    type Response = AppResponse;
+
+    async fn read_request<T>(&mut self, protocol: &Self::Protocol, io: &mut T) -> io::Result<Self::Request>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        Self::read(protocol, io, JSON_REQUEST_LIMIT).await
+    }
+
+    async fn read_response<T>(&mut self, protocol: &Self::Protocol, io: &mut T) -> io::Result<Self::Response>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        Self::read(protocol, io, JSON_RESPONSE_LIMIT).await
+    }
+
+    async fn write_request<T>(&mut self, protocol: &Self::Protocol, io: &mut T, req: Self::Request) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        self.write(protocol, io, req).await
+    }
+
+    async fn write_response<T>(&mut self, protocol: &Self::Protocol, io: &mut T, res: Self::Response) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        self.write(protocol, io, res).await
+    }
+}
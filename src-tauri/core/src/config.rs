@@ -0,0 +1,129 @@
+use std::fs;
+use std::path::Path;
+use libp2p::{Multiaddr, PeerId, core::multiaddr::Protocol};
+use serde::{Deserialize, Serialize};
+
+const NETWORK_CONFIG_FILE: &str = "network_config.json";
+
+/// Relay bundled with the app, used when no configuration has been saved yet.
+const DEFAULT_RELAY_ADDRESS: &str = "/ip4/35.212.216.37/udp/4001/quic-v1/p2p/12D3KooWGty8e23SZbBJTTmyLQjj8joaWU4cqPou46Gp6oGVE6UM";
+
+const DEFAULT_LISTEN_ADDRESS: &str = "/ip4/0.0.0.0/udp/0/quic-v1";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayConfig {
+    pub address: String,
+    /// Lower values are dialed first.
+    pub priority: u32,
+    pub enabled: bool,
+}
+
+/// Relays and `gossipsub` take effect as soon as the config is updated (the
+/// latter from the next session we host); the other fields are fixed when the
+/// swarm is built and need a restart.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    pub relays: Vec<RelayConfig>,
+    /// How many relays we hold a circuit reservation on at the same time.
+    pub max_reservations: usize,
+    /// When hosting, open a gossipsub topic for the session and use it for
+    /// sync, awareness and chat instead of per-peer requests.
+    pub gossipsub: bool,
+    /// Local addresses the swarm listens on. Needs a restart.
+    pub listen_addrs: Vec<String>,
+    /// Discover peers and their sessions on the local network. Needs a restart.
+    pub mdns: bool,
+    /// Compress large messages on the binary `/collab/2.0.0` protocol. Needs
+    /// a restart.
+    pub compression: bool,
+    /// How long a join request waits for the host to answer before it expires.
+    /// Needs a restart.
+    pub join_timeout_secs: u64,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            relays: vec![RelayConfig {
+                address: DEFAULT_RELAY_ADDRESS.to_string(),
+                priority: 0,
+                enabled: true,
+            }],
+            max_reservations: 2,
+            gossipsub: false,
+            listen_addrs: vec![DEFAULT_LISTEN_ADDRESS.to_string()],
+            mdns: true,
+            compression: true,
+            join_timeout_secs: 120,
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// Loads the config from the app data dir, falling back to the defaults if
+    /// the file is missing or unreadable.
+    pub fn load(app_data_dir: &Path) -> Self {
+        let path = app_data_dir.join(NETWORK_CONFIG_FILE);
+        match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                eprintln!("Ignoring invalid network config at {:?}: {}", path, e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self, app_data_dir: &Path) -> Result<(), String> {
+        fs::create_dir_all(app_data_dir).map_err(|e| e.to_string())?;
+        let bytes = serde_json::to_vec_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(app_data_dir.join(NETWORK_CONFIG_FILE), bytes).map_err(|e| e.to_string())
+    }
+
+    /// Checks that every relay address is a multiaddr ending in a peer id.
+    pub fn validate(&self) -> Result<(), String> {
+        for relay in &self.relays {
+            parse_relay_address(&relay.address)?;
+        }
+        for addr in &self.listen_addrs {
+            addr.parse::<Multiaddr>().map_err(|e| format!("Invalid listen address '{}': {}", addr, e))?;
+        }
+        if self.join_timeout_secs == 0 {
+            return Err("Join timeout must be at least one second".into());
+        }
+        Ok(())
+    }
+
+    /// Whether switching to `new` changes a field that only applies after a
+    /// restart.
+    pub fn restart_required(&self, new: &NetworkConfig) -> bool {
+        self.listen_addrs != new.listen_addrs
+            || self.mdns != new.mdns
+            || self.compression != new.compression
+            || self.join_timeout_secs != new.join_timeout_secs
+    }
+
+    /// Enabled relays ordered by priority, skipping any that fail to parse.
+    pub fn enabled_relays(&self) -> Vec<(PeerId, Multiaddr)> {
+        let mut relays: Vec<&RelayConfig> = self.relays.iter().filter(|r| r.enabled).collect();
+        relays.sort_by_key(|r| r.priority);
+        relays
+            .into_iter()
+            .filter_map(|r| parse_relay_address(&r.address).ok())
+            .collect()
+    }
+}
+
+pub fn parse_relay_address(address: &str) -> Result<(PeerId, Multiaddr), String> {
+    let addr: Multiaddr = address
+        .parse()
+        .map_err(|e| format!("Invalid relay address '{}': {}", address, e))?;
+    let peer_id = addr
+        .iter()
+        .find_map(|p| match p {
+            Protocol::P2p(id) => Some(id),
+            _ => None,
+        })
+        .ok_or_else(|| format!("Relay address '{}' has no /p2p/ peer id", address))?;
+    Ok((peer_id, addr))
+}
@@ -0,0 +1,161 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+/// Bytes `seal` adds to a payload.
+pub const SEAL_OVERHEAD: usize = NONCE_LEN + TAG_LEN;
+
+/// How long the key before a rotation still opens payloads, so edits already
+/// in flight when a member left are not lost.
+const PREVIOUS_KEY_GRACE: Duration = Duration::from_secs(30);
+
+/// A session key as handed to a guest, over the end-to-end Noise channel
+/// between host and guest.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyGrant {
+    /// Starts at 1 and goes up with every rotation; 0 marks plaintext.
+    pub epoch: u32,
+    #[serde(with = "serde_bytes")]
+    pub key: Vec<u8>,
+}
+
+impl fmt::Debug for KeyGrant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyGrant").field("epoch", &self.epoch).finish_non_exhaustive()
+    }
+}
+
+/// One epoch's key.
+#[derive(Clone)]
+pub struct SessionCipher {
+    epoch: u32,
+    cipher: XChaCha20Poly1305,
+}
+
+impl SessionCipher {
+    pub fn from_grant(grant: &KeyGrant) -> Result<Self, String> {
+        if grant.epoch == 0 || grant.key.len() != 32 {
+            return Err("Invalid session key".into());
+        }
+        Ok(Self { epoch: grant.epoch, cipher: XChaCha20Poly1305::new(Key::from_slice(&grant.key)) })
+    }
+
+    pub fn epoch(&self) -> u32 {
+        self.epoch
+    }
+
+    /// Encrypts `plaintext` under a fresh random nonce; `aad` binds the result
+    /// to its context (e.g. the document path) so it cannot be replayed elsewhere.
+    pub fn seal(&self, aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self.cipher
+            .encrypt(&nonce, Payload { msg: plaintext, aad })
+            .expect("encryption does not fail for in-memory buffers");
+        let mut sealed = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        sealed
+    }
+
+    pub fn open(&self, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, String> {
+        if sealed.len() < SEAL_OVERHEAD {
+            return Err("Encrypted payload is truncated".into());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+            .map_err(|_| "Encrypted payload failed to authenticate".to_string())
+    }
+}
+
+/// Keys of the session we host or joined.
+#[derive(Default)]
+pub struct SessionKeys {
+    current: Option<(SessionCipher, KeyGrant)>,
+    previous: Option<(SessionCipher, Instant)>,
+}
+
+impl SessionKeys {
+    pub fn current(&self) -> Option<&SessionCipher> {
+        self.current.as_ref().map(|(cipher, _)| cipher)
+    }
+
+    /// What to hand a guest so it can read and write the session.
+    pub fn grant(&self) -> Option<KeyGrant> {
+        self.current.as_ref().map(|(_, grant)| grant.clone())
+    }
+
+    /// Replaces the current key with a new random one, returning its grant.
+    /// Used by the host when the session starts and whenever a member leaves.
+    pub fn rotate(&mut self) -> KeyGrant {
+        let epoch = self.current().map_or(1, |c| c.epoch + 1);
+        let key = XChaCha20Poly1305::generate_key(&mut OsRng).to_vec();
+        let grant = KeyGrant { epoch, key };
+        self.install(&grant).expect("freshly generated key is valid");
+        grant
+    }
+
+    /// Switches to a key the host sent us. Older epochs are ignored so a
+    /// delayed grant cannot roll the key back.
+    pub fn install(&mut self, grant: &KeyGrant) -> Result<(), String> {
+        let cipher = SessionCipher::from_grant(grant)?;
+        if self.current().is_some_and(|c| c.epoch >= grant.epoch) {
+            return Ok(());
+        }
+        if let Some((old, _)) = self.current.take() {
+            self.previous = Some((old, Instant::now() + PREVIOUS_KEY_GRACE));
+        }
+        self.current = Some((cipher, grant.clone()));
+        Ok(())
+    }
+
+    /// Forgets every key, e.g. once we left the session.
+    pub fn clear(&mut self) {
+        self.current = None;
+        self.previous = None;
+    }
+
+    /// Encrypts with the current key; epoch 0 and the plaintext when we are
+    /// not in an encrypted session.
+    pub fn seal(&self, aad: &[u8], plaintext: Vec<u8>) -> (u32, Vec<u8>) {
+        match self.current() {
+            Some(cipher) => (cipher.epoch, cipher.seal(aad, &plaintext)),
+            None => (0, plaintext),
+        }
+    }
+
+    /// Decrypts a payload sealed under `epoch`. Plaintext is only accepted
+    /// while we hold no key, so a session cannot be downgraded.
+    pub fn open(&self, epoch: u32, aad: &[u8], data: Vec<u8>) -> Result<Vec<u8>, String> {
+        if epoch == 0 {
+            return match self.current {
+                Some(_) => Err("Refusing unencrypted payload in an encrypted session".into()),
+                None => Ok(data),
+            };
+        }
+        self.cipher(epoch)
+            .ok_or_else(|| format!("No session key for epoch {}", epoch))?
+            .open(aad, &data)
+    }
+
+    /// Whether `epoch` is newer than our key, i.e. the host rotated and we
+    /// have not received the grant yet.
+    pub fn is_behind(&self, epoch: u32) -> bool {
+        epoch > self.current().map_or(0, |c| c.epoch)
+    }
+
+    /// The key for `epoch`, if it is current or recently rotated out.
+    pub fn cipher(&self, epoch: u32) -> Option<&SessionCipher> {
+        if let Some(cipher) = self.current().filter(|c| c.epoch == epoch) {
+            return Some(cipher);
+        }
+        self.previous
+            .as_ref()
+            .filter(|(c, until)| c.epoch == epoch && Instant::now() < *until)
+            .map(|(c, _)| c)
+    }
+}
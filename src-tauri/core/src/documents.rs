@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use yrs::{Doc, ReadTxn, StateVector, Transact, Update};
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+
+/// Paths that travel over the sync channel but are not Yjs documents.
+fn is_document_path(path: &str) -> bool {
+    if path == ".heartbeat" {
+        return false;
+    }
+    let ext = path.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+    !matches!(ext.as_str(), "png" | "jpg" | "jpeg" | "gif" | "svg" | "webp")
+}
+
+/// Whether a v1-encoded update carries no changes (what `diff` returns when
+/// the other side is already up to date).
+pub fn is_empty_update(update: &[u8]) -> bool {
+    Update::decode_v1(update).map(|u| u.is_empty()).unwrap_or(true)
+}
+
+/// Authoritative copy of every collaborative document, keyed by file path.
+///
+/// Every update we send or receive is merged here, so the Rust side can answer
+/// sync requests on its own, even while the webview is reloading.
+#[derive(Default)]
+pub struct DocumentStore {
+    docs: HashMap<String, Doc>,
+}
+
+impl DocumentStore {
+    /// Merges a v1-encoded Yjs update into the document at `path`. Returns
+    /// `Ok(false)` for payloads that are not documents (heartbeats, assets).
+    pub fn apply_update(&mut self, path: &str, update: &[u8]) -> Result<bool, String> {
+        if update.is_empty() || !is_document_path(path) {
+            return Ok(false);
+        }
+        let update = Update::decode_v1(update).map_err(|e| format!("Invalid update for {}: {}", path, e))?;
+        let doc = self.docs.entry(path.to_string()).or_default();
+        doc.transact_mut()
+            .apply_update(update)
+            .map_err(|e| format!("Failed to apply update to {}: {}", path, e))?;
+        Ok(true)
+    }
+
+    /// The v1-encoded state vector of `path`, if we hold that document.
+    pub fn state_vector(&self, path: &str) -> Option<Vec<u8>> {
+        self.docs.get(path).map(|doc| doc.transact().state_vector().encode_v1())
+    }
+
+    /// Everything in `path` the holder of `state_vector` has not seen yet. An
+    /// empty state vector yields the whole document.
+    pub fn diff(&self, path: &str, state_vector: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        let Some(doc) = self.docs.get(path) else { return Ok(None) };
+        let sv = if state_vector.is_empty() {
+            StateVector::default()
+        } else {
+            StateVector::decode_v1(state_vector).map_err(|e| format!("Invalid state vector for {}: {}", path, e))?
+        };
+        Ok(Some(doc.transact().encode_diff_v1(&sv)))
+    }
+}
@@ -0,0 +1,26 @@
+use std::sync::Arc;
+use serde::Serialize;
+
+/// Receives the events the node reports (`p2p-sync`, `peer-connected`, ...).
+/// The Tauri shell forwards them to the webview; other front-ends can log or
+/// collect them.
+pub trait EventSink: Send + Sync + 'static {
+    fn emit(&self, event: &str, payload: serde_json::Value);
+}
+
+/// Cheaply clonable handle to an `EventSink` that serializes payloads.
+#[derive(Clone)]
+pub struct Events(Arc<dyn EventSink>);
+
+impl Events {
+    pub fn new(sink: impl EventSink) -> Self {
+        Self(Arc::new(sink))
+    }
+
+    pub fn emit<S: Serialize>(&self, event: &str, payload: S) {
+        match serde_json::to_value(payload) {
+            Ok(value) => self.0.emit(event, value),
+            Err(e) => eprintln!("Failed to serialize '{}' event: {}", event, e),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::collections::{HashSet, VecDeque};
+use libp2p::PeerId;
+
+/// How many recent update ids we remember for duplicate suppression.
+const SEEN_CAPACITY: usize = 4096;
+
+/// Assigns ids to the updates we originate and remembers the ids we have
+/// already delivered, so an update relayed by the host is applied and
+/// forwarded only once per peer.
+pub struct UpdateLog {
+    origin: String,
+    next: u64,
+    seen: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl UpdateLog {
+    pub fn new(origin: PeerId) -> Self {
+        Self { origin: origin.to_string(), next: 0, seen: HashSet::new(), order: VecDeque::new() }
+    }
+
+    /// A fresh id for an update we are about to send, already marked as seen.
+    pub fn next_id(&mut self) -> String {
+        self.next += 1;
+        let id = format!("{}:{}", self.origin, self.next);
+        self.first_seen(&id);
+        id
+    }
+
+    /// Records `id`, returning `false` if it was already delivered. Updates
+    /// without an id (older peers) are always treated as new.
+    pub fn first_seen(&mut self, id: &str) -> bool {
+        if id.is_empty() {
+            return true;
+        }
+        if !self.seen.insert(id.to_string()) {
+            return false;
+        }
+        self.order.push_back(id.to_string());
+        if self.order.len() > SEEN_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        true
+    }
+}
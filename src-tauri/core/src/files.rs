@@ -0,0 +1,258 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use crate::transfer;
+
+#[derive(Serialize, Clone)]
+pub struct FileEntry {
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+}
+
+/// Most files a received project may contain.
+pub const MAX_IMPORT_FILES: u64 = 50_000;
+/// Most bytes a received project may contain.
+pub const MAX_IMPORT_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Why an entry of a received project was left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipReason {
+    /// Empty, absolute, containing `..` or not valid UTF-8.
+    UnsafePath,
+    /// On Windows, names a device such as `NUL` or `COM1`, or contains a `:`.
+    DevicePath,
+    /// The entry, or a folder it would be written through, is a symlink.
+    Symlink,
+    /// Neither a regular file nor a folder.
+    NotAFile,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedEntry {
+    pub path: String,
+    pub reason: SkipReason,
+}
+
+/// What `save_incoming_project` did with a received project.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ImportReport {
+    pub written: usize,
+    pub deleted: usize,
+    pub skipped: Vec<SkippedEntry>,
+}
+
+/// Applies a project staged by a finished transfer to `dest_path`: removes
+/// the files the host deleted, then moves the received files in, overwriting
+/// files with the same name. Entries that could land outside `dest_path` are
+/// skipped and listed in the report, along with those the transfer already
+/// left out; a project over `MAX_IMPORT_FILES` or
+/// `MAX_IMPORT_BYTES` is refused before anything is touched.
+pub fn save_incoming_project(dest_path: &Path, staging: &Path) -> Result<ImportReport, String> {
+    let staged_files = staging.join(transfer::STAGED_FILES);
+    if !staged_files.is_dir() {
+        return Err("No received project to save".to_string());
+    }
+    let mut report = ImportReport::default();
+    if let Ok(bytes) = fs::read(staging.join(transfer::STAGED_SKIPPED)) {
+        report.skipped = serde_json::from_slice(&bytes).map_err(|e| format!("Invalid skipped list: {}", e))?;
+    }
+    let mut received = Vec::new();
+    collect_staged(&staged_files, Path::new(""), &mut received, &mut report.skipped)
+        .map_err(|e| format!("Failed to read received project: {}", e))?;
+    let total: u64 = received.iter().map(|(_, size)| size).sum();
+    if received.len() as u64 > MAX_IMPORT_FILES {
+        return Err(format!("Received project has {} files, more than the limit of {}", received.len(), MAX_IMPORT_FILES));
+    }
+    if total > MAX_IMPORT_BYTES {
+        return Err(format!("Received project has {} bytes, more than the limit of {}", total, MAX_IMPORT_BYTES));
+    }
+    fs::create_dir_all(dest_path).map_err(|e| e.to_string())?;
+
+    let deleted: Vec<String> = match fs::read(staging.join(transfer::STAGED_DELETIONS)) {
+        Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| format!("Invalid deletion list: {}", e))?,
+        Err(_) => Vec::new(),
+    };
+    for relative in deleted {
+        if let Err(reason) = check_project_path(&relative) {
+            report.skipped.push(SkippedEntry { path: relative, reason });
+            continue;
+        }
+        if crosses_symlink(dest_path, Path::new(&relative)) {
+            report.skipped.push(SkippedEntry { path: relative, reason: SkipReason::Symlink });
+            continue;
+        }
+        let path = dest_path.join(&relative);
+        if path.is_file() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to delete {}: {}", relative, e))?;
+            report.deleted += 1;
+        }
+        prune_empty_dirs(dest_path, &path);
+    }
+
+    for (relative, _) in received {
+        if crosses_symlink(dest_path, &relative) {
+            report.skipped.push(SkippedEntry { path: relative.to_string_lossy().to_string(), reason: SkipReason::Symlink });
+            continue;
+        }
+        let target = dest_path.join(&relative);
+        move_file(&staged_files.join(&relative), &target)
+            .map_err(|e| format!("Failed to save {}: {}", relative.display(), e))?;
+        report.written += 1;
+    }
+    fs::remove_dir_all(staging).ok();
+    Ok(report)
+}
+
+/// Removes the now empty parents of a deleted file, stopping at `root`.
+fn prune_empty_dirs(root: &Path, deleted: &Path) {
+    let mut dir = deleted.parent();
+    while let Some(current) = dir.filter(|d| *d != root && d.starts_with(root)) {
+        if fs::remove_dir(current).is_err() {
+            break;
+        }
+        dir = current.parent();
+    }
+}
+
+/// Lists the regular files under `dir` with their sizes, without following
+/// symlinks, and records everything else as skipped.
+fn collect_staged(dir: &Path, prefix: &Path, files: &mut Vec<(PathBuf, u64)>, skipped: &mut Vec<SkippedEntry>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let relative = prefix.join(entry.file_name());
+        let Some(name) = relative.to_str().map(str::to_string) else {
+            skipped.push(SkippedEntry { path: relative.to_string_lossy().to_string(), reason: SkipReason::UnsafePath });
+            continue;
+        };
+        let file_type = entry.file_type()?;
+        let checked = if file_type.is_symlink() { Err(SkipReason::Symlink) } else { check_project_path(&name) };
+        match checked {
+            Err(reason) => skipped.push(SkippedEntry { path: name, reason }),
+            Ok(()) if file_type.is_dir() => collect_staged(&entry.path(), &relative, files, skipped)?,
+            Ok(()) if file_type.is_file() => files.push((relative, entry.metadata()?.len())),
+            Ok(()) => skipped.push(SkippedEntry { path: name, reason: SkipReason::NotAFile }),
+        }
+    }
+    Ok(())
+}
+
+/// Whether `relative`, or a folder on the way to it, is a symlink under `root`.
+fn crosses_symlink(root: &Path, relative: &Path) -> bool {
+    let mut current = root.to_path_buf();
+    relative.components().any(|c| {
+        current.push(c);
+        fs::symlink_metadata(&current).is_ok_and(|m| m.file_type().is_symlink())
+    })
+}
+
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_err() {
+        // Staging and destination may be on different file systems
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+/// Checks that `relative` names something inside a project: a non-empty
+/// relative path without `..` or, on Windows, device names, so a peer cannot make us
+/// touch files outside of it.
+pub fn check_project_path(relative: &str) -> Result<(), SkipReason> {
+    let path = Path::new(relative);
+    if relative.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(SkipReason::UnsafePath);
+    }
+    if path.components().any(|c| is_device_name(&c.as_os_str().to_string_lossy())) {
+        return Err(SkipReason::DevicePath);
+    }
+    Ok(())
+}
+
+pub fn is_project_path(relative: &str) -> bool {
+    check_project_path(relative).is_ok()
+}
+
+/// Windows opens a device instead of a file for these names, whatever the
+/// extension; `:` would address a drive or an alternate data stream. Other
+/// platforms treat them as ordinary names.
+#[cfg(windows)]
+fn is_device_name(name: &str) -> bool {
+    if name.contains(':') {
+        return true;
+    }
+    let stem = name.split('.').next().unwrap_or_default().trim_end_matches(' ').to_ascii_uppercase();
+    match stem.as_bytes() {
+        b"CON" | b"PRN" | b"AUX" | b"NUL" => true,
+        [b'C', b'O', b'M', digit] | [b'L', b'P', b'T', digit] => (b'1'..=b'9').contains(digit),
+        _ => false,
+    }
+}
+
+#[cfg(not(windows))]
+fn is_device_name(_name: &str) -> bool {
+    false
+}
+
+/// Lists a directory for the explorer: folders first, hidden files skipped
+/// except the session metadata.
+pub fn read_directory(path: &Path) -> Result<Vec<FileEntry>, String> {
+    let paths = fs::read_dir(path).map_err(|e| e.to_string())?;
+    let mut entries = Vec::new();
+
+    for path in paths {
+        let path = path.map_err(|e| e.to_string())?;
+        let file_type = path.file_type().map_err(|e| e.to_string())?;
+        let file_name = path.file_name().into_string().map_err(|_| "Invalid UTF-8".to_string())?;
+        let file_path = path.path().to_string_lossy().to_string();
+
+        if file_name.starts_with('.') && file_name != ".collab_meta.json" { continue; }
+
+        entries.push(FileEntry {
+            name: file_name,
+            path: file_path,
+            is_dir: file_type.is_dir(),
+        });
+    }
+
+    entries.sort_by(|a, b| {
+        if a.is_dir == b.is_dir {
+            a.name.cmp(&b.name)
+        } else if a.is_dir {
+            std::cmp::Ordering::Less
+        } else {
+            std::cmp::Ordering::Greater
+        }
+    });
+
+    Ok(entries)
+}
+
+pub fn read_file(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| e.to_string())
+}
+
+/// Writes `content` and stages the file if it lives inside a git work tree.
+pub fn write_file(path: &Path, content: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).ok();
+    }
+
+    fs::write(path, content).map_err(|e| e.to_string())?;
+
+    if let Ok(repo) = Repository::discover(path) {
+        if let Some(workdir) = repo.workdir() {
+            if let Ok(relative_path) = path.strip_prefix(workdir) {
+                let mut index = repo.index().map_err(|e| e.to_string())?;
+                index.add_path(relative_path).map_err(|_| "Failed to add path")?;
+                index.write().ok();
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use std::path::Path;
+use std::process::Command;
+use git2::Repository;
+
+/// Keeps git from opening GUI prompts or talking to the desktop session.
+/// `drop_agent` also hides the SSH agent so only the configured key is used.
+fn sanitize_env(cmd: &mut Command, drop_agent: bool) {
+    cmd.env("GIT_TERMINAL_PROMPT", "0");
+
+    // Aggressively sanitize environment on Linux to prevent X11/Wayland connection theft
+    #[cfg(target_os = "linux")]
+    {
+        cmd.env_remove("DISPLAY");
+        cmd.env_remove("GDK_BACKEND");
+        cmd.env_remove("GTK_MODULES");
+        cmd.env_remove("SSH_ASKPASS");
+        cmd.env_remove("GIT_ASKPASS");
+        if drop_agent {
+            cmd.env_remove("SSH_AUTH_SOCK"); // Prevent interference from system agent
+        }
+        cmd.env_remove("XAUTHORITY");
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = drop_agent;
+        cmd.env("SSH_ASKPASS", "false");
+        cmd.env("GIT_ASKPASS", "false");
+    }
+}
+
+fn use_ssh_key(cmd: &mut Command, ssh_key_path: &str) {
+    if !ssh_key_path.trim().is_empty() {
+        #[cfg(target_os = "windows")]
+        let ssh_cmd = format!("ssh -i \"{}\"", ssh_key_path.replace("\\", "\\\\"));
+        #[cfg(not(target_os = "windows"))]
+        let ssh_cmd = format!("ssh -i \"{}\"", ssh_key_path);
+        cmd.env("GIT_SSH_COMMAND", ssh_cmd);
+    }
+}
+
+pub fn init_repo(path: &str) -> Result<String, String> {
+    match Repository::init(path) {
+        Ok(_) => Ok(format!("Initialized Git repository in {}", path)),
+        Err(e) => Err(format!("Failed to init repo: {}", e)),
+    }
+}
+
+pub fn get_remote_origin(path: &str) -> Result<String, String> {
+    let repo = Repository::open(path).map_err(|e| e.to_string())?;
+    let remote = repo.find_remote("origin").map_err(|_| "No remote 'origin' found".to_string())?;
+    let url = remote.url().ok_or("Remote 'origin' has no URL")?;
+    Ok(url.to_string())
+}
+
+pub fn set_remote_origin(path: &str, url: &str) -> Result<String, String> {
+    let repo = Repository::open(path).map_err(|e| e.to_string())?;
+    if repo.find_remote("origin").is_ok() {
+        repo.remote_set_url("origin", url).map_err(|e| e.to_string())?;
+    } else {
+        repo.remote("origin", url).map_err(|e| e.to_string())?;
+    }
+    Ok(format!("Remote 'origin' set to {}", url))
+}
+
+pub fn pull(path: &Path, ssh_key_path: &str) -> Result<String, String> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(path);
+    cmd.arg("pull");
+    use_ssh_key(&mut cmd, ssh_key_path);
+    sanitize_env(&mut cmd, true);
+
+    let output = cmd.output().map_err(|e| format!("Git pull failed: {}", e))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).to_string())
+    }
+}
+
+/// Commits everything staged as "Auto-sync" and pushes it.
+pub fn push(path: &Path, ssh_key_path: &str) -> Result<String, String> {
+    let mut commit_cmd = Command::new("git");
+    commit_cmd.current_dir(path);
+    commit_cmd.args(["commit", "-m", "Auto-sync"]);
+    sanitize_env(&mut commit_cmd, false);
+
+    let _ = commit_cmd.output();
+
+    let mut cmd = Command::new("git");
+    cmd.current_dir(path);
+    cmd.arg("push");
+    use_ssh_key(&mut cmd, ssh_key_path);
+    sanitize_env(&mut cmd, true);
+
+    let output = cmd.output().map_err(|e| format!("Git command failed: {}", e))?;
+
+    if output.status.success() {
+        Ok("Push successful".to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).to_string())
+    }
+}
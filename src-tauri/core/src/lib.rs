@@ -0,0 +1,30 @@
+//! Everything my-editor does without a window: the libp2p session protocol and
+//! swarm loop, document and presence state, and project file/git helpers.
+//! `my_editor_lib` wraps this crate in Tauri commands. Diagnostics are logged
+//! to stderr so stdout stays free for embedders such as the CLI's event stream.
+
+pub mod protocol;
+pub mod state;
+pub mod network;
+pub mod config;
+pub mod peers;
+pub mod documents;
+pub mod awareness;
+pub mod events;
+pub mod files;
+pub mod git;
+pub mod transfer;
+pub mod merkle;
+pub mod codec;
+pub mod version;
+pub mod auth;
+pub mod crypto;
+pub mod workspace;
+pub mod trust;
+pub mod relay;
+mod fanout;
+
+pub use events::{EventSink, Events};
+pub use network::start_p2p_node;
+pub use protocol::SwarmCommand;
+pub use state::PeerState;
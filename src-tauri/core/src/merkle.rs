@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::transfer::SKIPPED_DIRS;
+
+/// A node of a project's content-hash tree. A directory's hash covers the
+/// names and hashes of its children, so equal hashes mean equal subtrees.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TreeNode {
+    File { hash: String },
+    Dir { hash: String, children: BTreeMap<String, TreeNode> },
+}
+
+impl TreeNode {
+    pub fn hash(&self) -> &str {
+        match self {
+            TreeNode::File { hash } | TreeNode::Dir { hash, .. } => hash,
+        }
+    }
+
+    fn files(&self, prefix: &str, out: &mut Vec<String>) {
+        match self {
+            TreeNode::File { .. } => out.push(prefix.to_string()),
+            TreeNode::Dir { children, .. } => {
+                for (name, child) in children {
+                    child.files(&join(prefix, name), out);
+                }
+            }
+        }
+    }
+}
+
+/// What a guest holding `base` needs to end up with the host's tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeDiff {
+    /// Files that are new or whose content differs.
+    pub changed: Vec<String>,
+    /// Files the guest has that the host does not.
+    pub deleted: Vec<String>,
+}
+
+/// Content-hash (Merkle) tree of a project folder, skipping the same
+/// directories as the project transfer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectTree {
+    pub root: TreeNode,
+}
+
+impl ProjectTree {
+    /// Hashes every file below `root`. A missing folder yields an empty tree.
+    pub fn build(root: &Path) -> io::Result<Self> {
+        if !root.exists() {
+            return Ok(Self { root: dir_node(BTreeMap::new()) });
+        }
+        Ok(Self { root: build_dir(root)? })
+    }
+
+    pub fn hash(&self) -> &str {
+        self.root.hash()
+    }
+
+    /// Every file path in the tree, `/`-separated.
+    pub fn files(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        self.root.files("", &mut out);
+        out
+    }
+
+    /// Compares against the guest's `base`, skipping subtrees whose hashes match.
+    pub fn diff(&self, base: &ProjectTree) -> TreeDiff {
+        let mut diff = TreeDiff::default();
+        diff_node(&self.root, Some(&base.root), "", &mut diff);
+        diff
+    }
+}
+
+fn join(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() { name.to_string() } else { format!("{}/{}", prefix, name) }
+}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn dir_node(children: BTreeMap<String, TreeNode>) -> TreeNode {
+    let mut hasher = Sha256::new();
+    for (name, child) in &children {
+        hasher.update(name.as_bytes());
+        hasher.update([0]);
+        hasher.update(child.hash().as_bytes());
+    }
+    TreeNode::Dir { hash: hex(&hasher.finalize()), children }
+}
+
+fn build_dir(dir: &Path) -> io::Result<TreeNode> {
+    let mut children = BTreeMap::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if !SKIPPED_DIRS.contains(&name.as_str()) {
+                children.insert(name, build_dir(&entry.path())?);
+            }
+        } else if file_type.is_file() {
+            let mut hasher = Sha256::new();
+            io::copy(&mut std::fs::File::open(entry.path())?, &mut hasher)?;
+            children.insert(name, TreeNode::File { hash: hex(&hasher.finalize()) });
+        }
+    }
+    Ok(dir_node(children))
+}
+
+fn diff_node(ours: &TreeNode, theirs: Option<&TreeNode>, prefix: &str, diff: &mut TreeDiff) {
+    if theirs.is_some_and(|t| t.hash() == ours.hash()) {
+        return;
+    }
+    match (ours, theirs) {
+        (TreeNode::Dir { children, .. }, Some(TreeNode::Dir { children: base, .. })) => {
+            for (name, child) in children {
+                diff_node(child, base.get(name), &join(prefix, name), diff);
+            }
+            for (name, gone) in base {
+                if !children.contains_key(name) {
+                    gone.files(&join(prefix, name), &mut diff.deleted);
+                }
+            }
+        }
+        (TreeNode::Dir { children, .. }, other) => {
+            // A file now stands where the guest had a directory, or the reverse
+            if let Some(other) = other {
+                other.files(prefix, &mut diff.deleted);
+            }
+            for (name, child) in children {
+                diff_node(child, None, &join(prefix, name), diff);
+            }
+        }
+        (TreeNode::File { .. }, other) => {
+            if let Some(dir @ TreeNode::Dir { .. }) = other {
+                dir.files(prefix, &mut diff.deleted);
+            }
+            diff.changed.push(prefix.to_string());
+        }
+    }
+}
@@ -0,0 +1,1642 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use std::fs; 
+use std::path::{Path, PathBuf};
+use libp2p::{
+    swarm::{NetworkBehaviour, SwarmEvent, dial_opts::DialOpts, behaviour::toggle::Toggle},
+    SwarmBuilder, Swarm, PeerId, Multiaddr, 
+    request_response::{self, ProtocolSupport},
+    identity,
+    relay::client as relay_client,
+    dcutr,
+    identify,
+    ping,
+    mdns,
+    gossipsub,
+    noise, // Required for relay transport upgrade
+    yamux, // Required for relay transport upgrade
+    core::upgrade::Version, // Required for upgrade version
+    Transport
+};
+use tokio::sync::mpsc::Receiver;
+use futures::stream::StreamExt; 
+use serde::Serialize; 
+
+use crate::protocol::{AppRequest, AppResponse, AwarenessEntry, GossipMessage, JoinProfile, Reply, SwarmCommand};
+use crate::state::{DiscoveredPeer, PeerState};
+use crate::config::NetworkConfig;
+use crate::relay::RelayPool;
+use crate::peers::{PeerRole, PeerTracker, SessionPeer, SessionRole};
+use crate::documents::is_empty_update;
+use crate::fanout::UpdateLog;
+use crate::events::Events;
+use crate::merkle::ProjectTree;
+use crate::files;
+use crate::trust::{TrustLevel, TrustList};
+use crate::auth::{GuessLimiter, PasswordExchange, SessionKey, Side};
+use crate::version::{PeerInfo, PROTOCOL_VERSION, APP_VERSION};
+use crate::codec::{CollabCodec, BINARY_PROTOCOL, JSON_PROTOCOL};
+use crate::transfer::{self, Direction, TransferProgress, TRANSFER_PROTOCOL};
+
+fn get_local_ip() -> Option<std::net::IpAddr> {
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("8.8.8.8:53").ok()?;
+    socket.local_addr().ok().map(|addr| addr.ip())
+}
+
+#[derive(NetworkBehaviour)]
+struct MyBehaviour {
+    request_response: request_response::Behaviour<CollabCodec>,
+    relay_client: relay_client::Behaviour,
+    dcutr: dcutr::Behaviour,
+    identify: identify::Behaviour,
+    ping: ping::Behaviour,
+    mdns: Toggle<mdns::tokio::Behaviour>,
+    gossipsub: gossipsub::Behaviour,
+    stream: libp2p_stream::Behaviour,
+}
+
+/// Larger gossip payloads (e.g. a full document state) go over request-response.
+const GOSSIP_MAX_TRANSMIT_SIZE: usize = 1024 * 1024;
+
+/// Join requests stay open while the host decides, so requests may take this
+/// much longer than the host's own expiry before the transport gives up.
+const JOIN_TIMEOUT_GRACE: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Clone)]
+struct SyncEvent {
+    path: String,
+    data: Vec<u8>,
+}
+
+#[derive(Serialize, Clone)]
+struct SyncRequestEvent {
+    path: String,
+}
+
+#[derive(Serialize, Clone)]
+struct FileContentEvent {
+    path: String,
+    data: Vec<u8>,
+}
+
+#[derive(Serialize, Clone)]
+struct ChatEvent {
+    peer_id: String,
+    text: String,
+    /// Whether `peer_id` is the peer that sent us the message, rather than a
+    /// member the host names when relaying it.
+    verified: bool,
+}
+
+/// Payload of `join-requested`.
+#[derive(Serialize, Clone)]
+struct JoinRequestEvent {
+    peer_id: String,
+    profile: JoinProfile,
+    app_version: String,
+}
+
+/// Payload of `join-rejected`.
+#[derive(Serialize, Clone)]
+struct JoinRejectedEvent {
+    peer_id: String,
+    reason: Option<String>,
+}
+
+/// Payload of `join-incompatible` and `join-auth-failed`: a join we refused
+/// before asking the user, or, on the guest, `join-password-required`.
+#[derive(Serialize, Clone)]
+struct JoinRefusedEvent {
+    peer_id: String,
+    reason: String,
+}
+
+/// Payload of `peer-kicked` on the host.
+#[derive(Serialize, Clone)]
+struct PeerKickedEvent {
+    peer_id: String,
+    banned: bool,
+}
+
+/// Payload of `session-ended` on a guest the host removed.
+#[derive(Serialize, Clone)]
+struct SessionEndedEvent {
+    peer_id: String,
+    reason: String,
+}
+
+/// Payload of `permission-denied`: something a peer sent that its role or the
+/// project boundary does not allow, dropped instead of applied.
+#[derive(Serialize, Clone)]
+struct PermissionDeniedEvent {
+    peer_id: String,
+    action: &'static str,
+    path: String,
+    reason: String,
+}
+
+#[derive(Serialize, Clone)]
+struct AwarenessChangedEvent {
+    updated: Vec<AwarenessEntry>,
+    removed: Vec<u64>,
+}
+
+impl AwarenessChangedEvent {
+    fn from_entries(entries: &[AwarenessEntry]) -> Self {
+        Self {
+            updated: entries.iter().filter(|e| e.state.is_some()).cloned().collect(),
+            removed: entries.iter().filter(|e| e.state.is_none()).map(|e| e.client_id).collect(),
+        }
+    }
+}
+
+/// Every session member we exchange traffic with, minus `except` (usually the
+/// peer a message came from).
+fn session_targets(state: &PeerState, current_host: Option<PeerId>, except: Option<PeerId>) -> Vec<PeerId> {
+    let mut targets: Vec<PeerId> = state
+        .active_peers
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .filter_map(|p| p.parse::<PeerId>().ok())
+        .collect();
+    if let Some(host) = current_host {
+        if !targets.contains(&host) {
+            targets.push(host);
+        }
+    }
+    targets.retain(|p| Some(*p) != except);
+    targets
+}
+
+/// A join we started and have not heard back about yet.
+struct PendingJoin {
+    host: PeerId,
+    /// Set once the request is on the wire, i.e. after the connection is up.
+    request: Option<request_response::OutboundRequestId>,
+    profile: JoinProfile,
+    password: Option<String>,
+    /// Our half of the password exchange while waiting for the host's.
+    exchange: Option<PasswordExchange>,
+    /// Our key confirmation once the password exchange succeeded.
+    auth: Option<Vec<u8>>,
+    tree: Option<ProjectTree>,
+    reply: Reply,
+}
+
+impl PendingJoin {
+    /// Opens the handshake: the password exchange if we have a password,
+    /// otherwise the join itself.
+    fn first_request(&mut self, local: &PeerId) -> AppRequest {
+        match &self.password {
+            Some(password) => {
+                let (exchange, pake) = PasswordExchange::start(password, &self.host, local);
+                self.exchange = Some(exchange);
+                AppRequest::Authenticate { pake }
+            }
+            None => self.request(),
+        }
+    }
+
+    fn request(&self) -> AppRequest {
+        AppRequest::Join { profile: self.profile.clone(), tree: self.tree.clone(), info: Some(PeerInfo::local()), auth: self.auth.clone() }
+    }
+}
+
+/// A guest waiting for the user to approve or reject its join.
+struct JoiningGuest {
+    info: PeerInfo,
+    profile: JoinProfile,
+    expires: Instant,
+}
+
+/// Answers the pending join from `peer` with a refusal and forgets it. Returns
+/// `false` if there was no such request or the guest stopped waiting.
+fn refuse_join(swarm: &mut Swarm<MyBehaviour>, state: &PeerState, peer: &PeerId, reason: Option<String>, expired: bool) -> bool {
+    let channel = state.pending_invites.lock().unwrap_or_else(|e| e.into_inner()).remove(&peer.to_string());
+    state.transfers.lock().unwrap_or_else(|e| e.into_inner()).take_baseline(peer);
+    let Some(channel) = channel else { return false };
+    let response = AppResponse::Join { accepted: false, topic: None, reason, info: Some(PeerInfo::local()), expired, password_required: false, session_key: None, role: SessionRole::default() };
+    swarm.behaviour_mut().request_response.send_response(channel, response).is_ok()
+}
+
+/// How often discovered peers are asked again whether they are hosting.
+const DISCOVERY_REFRESH: Duration = Duration::from_secs(10);
+
+/// How long a removed member gets to acknowledge `SessionEnded` before we
+/// close its connections anyway.
+const KICK_GRACE: Duration = Duration::from_secs(5);
+
+/// A pending join the host is letting in.
+struct Admission {
+    peer: PeerId,
+    /// Project folder shared with the guest.
+    root: PathBuf,
+    project: String,
+    role: SessionRole,
+    joining: Option<JoiningGuest>,
+}
+
+/// Answers the pending join of `admission.peer` with an acceptance and makes
+/// it a member of the session, opening the session topic and key with the
+/// first guest.
+fn accept_join(
+    swarm: &mut Swarm<MyBehaviour>,
+    events: &Events,
+    state: &PeerState,
+    tracker: &PeerTracker,
+    session_topic: &mut Option<(String, gossipsub::Sha256Topic)>,
+    local_peer_id: &PeerId,
+    admission: Admission,
+) -> Result<(), String> {
+    let Admission { peer, root, project, role, joining } = admission;
+    let channel = state.pending_invites.lock().unwrap_or_else(|e| e.into_inner()).remove(&peer.to_string());
+    let Some(channel) = channel else {
+        return Err(format!("No pending join request from {}", peer));
+    };
+
+    // Open the session topic with the first guest if gossip is enabled
+    let gossip_enabled = state.network_config.lock().unwrap_or_else(|e| e.into_inner()).gossipsub;
+    if gossip_enabled && session_topic.is_none() {
+        let name = format!("{}/{}", local_peer_id, project);
+        let topic = topic_for_session(&name);
+        match swarm.behaviour_mut().gossipsub.subscribe(&topic) {
+            Ok(_) => *session_topic = Some((name, topic)),
+            Err(e) => eprintln!("Failed to open session topic: {:?}", e),
+        }
+    }
+    let topic = session_topic.as_ref().map(|(name, _)| name.clone());
+    // The session key is created with the first guest and shared with every one
+    let session_key = {
+        let mut keys = state.session_keys.lock().unwrap_or_else(|e| e.into_inner());
+        keys.grant().unwrap_or_else(|| keys.rotate())
+    };
+    if swarm.behaviour_mut().request_response.send_response(
+        channel,
+        AppResponse::Join { accepted: true, topic, reason: None, info: Some(PeerInfo::local()), expired: false, password_required: false, session_key: Some(session_key), role }
+    ).is_err() {
+        state.transfers.lock().unwrap_or_else(|e| e.into_inner()).take_baseline(&peer);
+        return Err(format!("Peer {} is no longer waiting for an answer", peer));
+    }
+    *state.shared_project.lock().unwrap_or_else(|e| e.into_inner()) = Some(root);
+    *state.local_role.lock().unwrap_or_else(|e| e.into_inner()) = Some(SessionRole::Host);
+    let (info, profile) = joining.map(|g| (g.info, g.profile)).unzip();
+    add_session_peer(events, state, tracker, peer, role, info.as_ref(), profile);
+    // Bring the new guest up to date with everyone's cursors
+    let entries = state.awareness.lock().unwrap_or_else(|e| e.into_inner()).states();
+    if !entries.is_empty() {
+        swarm.behaviour_mut().request_response.send_request(&peer, AppRequest::Awareness { entries });
+    }
+    Ok(())
+}
+
+fn finish_transfer(events: &Events, peer: PeerId, direction: Direction, outcome: std::io::Result<TransferProgress>) {
+    match outcome {
+        Ok(report) => events.emit("transfer-complete", report),
+        Err(e) => {
+            eprintln!("Project transfer with {} failed: {}", peer, e);
+            events.emit("transfer-failed", TransferProgress::failed(peer, direction, e.to_string()));
+        }
+    }
+}
+
+/// Streams the shared project to guests of our session that ask for it.
+async fn serve_transfers(mut incoming: libp2p_stream::IncomingStreams, events: Events, state: PeerState) {
+    while let Some((peer, mut stream)) = incoming.next().await {
+        let is_guest = state.session_peers.lock().unwrap_or_else(|e| e.into_inner())
+            .get(&peer.to_string())
+            .is_some_and(|p| p.role == PeerRole::Guest);
+        let root = state.shared_project.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let Some(root) = root.filter(|_| is_guest) else {
+            eprintln!("Refusing project transfer to {}: not a guest of our session", peer);
+            continue;
+        };
+        let baseline = state.transfers.lock().unwrap_or_else(|e| e.into_inner()).take_baseline(&peer);
+        let cipher = state.session_keys.lock().unwrap_or_else(|e| e.into_inner()).current().cloned();
+
+        // Hold the registry until the handle is in, so a quick finish cannot race it
+        let mut transfers = state.transfers.lock().unwrap_or_else(|e| e.into_inner());
+        let (events, registry) = (events.clone(), state.transfers.clone());
+        let task = tokio::spawn(async move {
+            let outcome = transfer::send_project(&mut stream, &root, baseline, cipher, peer, |p| events.emit("transfer-progress", p)).await;
+            registry.lock().unwrap_or_else(|e| e.into_inner()).remove(&peer);
+            finish_transfer(&events, peer, Direction::Send, outcome);
+        });
+        transfers.insert(&peer, task.abort_handle());
+    }
+}
+
+/// Pulls the host's project into `staging` after our join was accepted.
+fn fetch_project(mut control: libp2p_stream::Control, host: PeerId, staging: &Path, events: &Events, state: &PeerState) {
+    let cipher = state.session_keys.lock().unwrap_or_else(|e| e.into_inner()).current().cloned();
+    let mut transfers = state.transfers.lock().unwrap_or_else(|e| e.into_inner());
+    let (events, registry, staging) = (events.clone(), state.transfers.clone(), staging.to_path_buf());
+    let task = tokio::spawn(async move {
+        let outcome = match control.open_stream(host, TRANSFER_PROTOCOL).await {
+            Ok(mut stream) => transfer::receive_project(&mut stream, &staging, cipher, host, |p| events.emit("transfer-progress", p)).await,
+            Err(e) => Err(std::io::Error::other(e.to_string())),
+        };
+        registry.lock().unwrap_or_else(|e| e.into_inner()).remove(&host);
+        finish_transfer(&events, host, Direction::Receive, outcome);
+    });
+    transfers.insert(&host, task.abort_handle());
+}
+
+/// Gossipsub topic for a session name (`<host peer id>/<project>`). The topic is
+/// hashed so the project name is not visible to other mesh members.
+fn topic_for_session(name: &str) -> gossipsub::Sha256Topic {
+    gossipsub::Sha256Topic::new(format!("/my-editor/session/{}", name))
+}
+
+/// Sends session traffic to every member: published on the session topic when
+/// there is one, otherwise as a request to each member except `except`.
+fn broadcast(
+    swarm: &mut Swarm<MyBehaviour>,
+    state: &PeerState,
+    current_host: Option<PeerId>,
+    topic: Option<&gossipsub::Sha256Topic>,
+    except: Option<PeerId>,
+    message: GossipMessage,
+) {
+    if let Some(topic) = topic {
+        match serde_json::to_vec(&message) {
+            Ok(bytes) if bytes.len() <= GOSSIP_MAX_TRANSMIT_SIZE => {
+                match swarm.behaviour_mut().gossipsub.publish(topic.clone(), bytes) {
+                    Ok(_) | Err(gossipsub::PublishError::Duplicate) => return,
+                    Err(e) => eprintln!("Gossipsub publish failed, falling back to direct requests: {:?}", e),
+                }
+            },
+            Ok(_) => {},
+            Err(e) => eprintln!("Failed to encode gossip message: {}", e),
+        }
+    }
+
+    let request = AppRequest::from(message);
+    for peer in session_targets(state, current_host, except) {
+        swarm.behaviour_mut().request_response.send_request(&peer, request.clone());
+    }
+}
+
+/// Merges a sync payload into the Rust-side document store.
+fn apply_to_store(state: &PeerState, path: &str, data: &[u8]) {
+    if let Err(e) = state.documents.lock().unwrap_or_else(|e| e.into_inner()).apply_update(path, data) {
+        eprintln!("{}", e);
+    }
+}
+
+/// Seals a `Sync`, `FileContent` or diff payload for the session. `aad` names
+/// what the payload is, so it cannot be passed off as another document.
+fn seal(state: &PeerState, aad: String, data: Vec<u8>) -> (u32, Vec<u8>) {
+    state.session_keys.lock().unwrap_or_else(|e| e.into_inner()).seal(aad.as_bytes(), data)
+}
+
+/// Opens a payload from `peer`, dropping it if it was not sealed with a key of
+/// our session.
+fn open(state: &PeerState, peer: &PeerId, aad: String, epoch: u32, data: Vec<u8>) -> Option<Vec<u8>> {
+    match state.session_keys.lock().unwrap_or_else(|e| e.into_inner()).open(epoch, aad.as_bytes(), data) {
+        Ok(data) => Some(data),
+        Err(e) => {
+            eprintln!("Dropping {} from {}: {}", aad, peer, e);
+            None
+        }
+    }
+}
+
+/// Notes `path` when a guest could not open an update for it because the host
+/// already seals with a key whose grant has not reached us, so the document
+/// can be resynced once the grant arrives.
+fn note_missed_update(state: &PeerState, current_host: Option<PeerId>, epoch: u32, path: &str, missed: &mut HashMap<String, u32>) {
+    if current_host.is_some() && state.session_keys.lock().unwrap_or_else(|e| e.into_inner()).is_behind(epoch) {
+        let newest = missed.entry(path.to_string()).or_default();
+        *newest = (*newest).max(epoch);
+    }
+}
+
+/// Replaces the key of the session we host after a member left, so it cannot
+/// read what follows, and hands the new key to every remaining guest.
+fn rotate_session_key(swarm: &mut Swarm<MyBehaviour>, events: &Events, state: &PeerState, current_host: Option<PeerId>) {
+    if current_host.is_some() {
+        return;
+    }
+    let grant = {
+        let mut keys = state.session_keys.lock().unwrap_or_else(|e| e.into_inner());
+        if keys.current().is_none() {
+            return;
+        }
+        keys.rotate()
+    };
+    eprintln!("Rotated session key to epoch {}", grant.epoch);
+    for peer in session_targets(state, None, None) {
+        swarm.behaviour_mut().request_response.send_request(&peer, AppRequest::SessionKey { grant: grant.clone() });
+    }
+    events.emit("session-key-rotated", grant.epoch);
+}
+
+/// Role of `peer` in our session as far as we know: our host, or a guest of
+/// the session we host.
+fn member_role(state: &PeerState, current_host: Option<PeerId>, peer: &PeerId) -> Option<SessionRole> {
+    if Some(*peer) == current_host {
+        return Some(SessionRole::Host);
+    }
+    state.session_peers.lock().unwrap_or_else(|e| e.into_inner()).get(&peer.to_string()).map(|p| p.session_role)
+}
+
+/// Checks that a member with `role` may change `path`, logging the violation
+/// and emitting `permission-denied` when not.
+fn authorize_edit(events: &Events, peer: &PeerId, role: Option<SessionRole>, action: &'static str, path: &str) -> bool {
+    let reason = if !files::is_project_path(path) {
+        "the path leaves the project"
+    } else {
+        match role {
+            Some(role) if role.can_edit() => return true,
+            Some(_) => "viewers cannot edit the session",
+            None => "not a member of the session",
+        }
+    };
+    eprintln!("Dropping {} of '{}' from {}: {}", action, path, peer, reason);
+    events.emit("permission-denied", PermissionDeniedEvent { peer_id: peer.to_string(), action, path: path.to_string(), reason: reason.to_string() });
+    false
+}
+
+/// Like `authorize_edit` for requests that only read the session.
+fn authorize_read(events: &Events, peer: &PeerId, role: Option<SessionRole>, path: &str) -> bool {
+    if role.is_some() && files::is_project_path(path) {
+        return true;
+    }
+    let reason = if role.is_none() { "not a member of the session" } else { "the path leaves the project" };
+    eprintln!("Refusing to share '{}' with {}: {}", path, peer, reason);
+    events.emit("permission-denied", PermissionDeniedEvent { peer_id: peer.to_string(), action: "read", path: path.to_string(), reason: reason.to_string() });
+    false
+}
+
+/// Whether our own role lets us change the session.
+fn can_edit_locally(state: &PeerState) -> bool {
+    state.local_role.lock().unwrap_or_else(|e| e.into_inner()).is_none_or(|role| role.can_edit())
+}
+
+/// Whether presence and chat from `peer` belong to our session: any member
+/// while hosting; only the host once we joined, since it relays the others.
+fn speaks_for_session(state: &PeerState, current_host: Option<PeerId>, peer: &PeerId) -> bool {
+    match current_host {
+        Some(host) => host == *peer,
+        None => member_role(state, None, peer).is_some(),
+    }
+}
+
+/// Merges awareness entries from `origin`; only our host may speak for
+/// clients other than its own.
+fn apply_awareness(state: &PeerState, current_host: Option<PeerId>, origin: PeerId, entries: Vec<AwarenessEntry>) -> Vec<AwarenessEntry> {
+    let mut awareness = state.awareness.lock().unwrap_or_else(|e| e.into_inner());
+    if Some(origin) == current_host { awareness.apply_from_host(origin, entries) } else { awareness.apply(origin, entries) }
+}
+
+/// Forgets the session we joined: its topic, key and our role in it.
+fn leave_session(swarm: &mut Swarm<MyBehaviour>, state: &PeerState, session_topic: &mut Option<(String, gossipsub::Sha256Topic)>) {
+    if let Some((_, topic)) = session_topic.take() {
+        swarm.behaviour_mut().gossipsub.unsubscribe(&topic);
+    }
+    state.session_keys.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    *state.local_role.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Records `peer` as a session member and announces it to the UI.
+fn add_session_peer(
+    events: &Events,
+    state: &PeerState,
+    tracker: &PeerTracker,
+    peer: PeerId,
+    session_role: SessionRole,
+    info: Option<&PeerInfo>,
+    profile: Option<JoinProfile>,
+) {
+    let entry = SessionPeer {
+        peer_id: peer.to_string(),
+        role: if session_role == SessionRole::Host { PeerRole::Host } else { PeerRole::Guest },
+        session_role,
+        transport: tracker.transport(&peer),
+        app_version: info.map(|i| i.app_version.clone()),
+        capabilities: info.map(|i| PeerInfo::local().negotiate(i)).unwrap_or_default(),
+        profile,
+    };
+    state.active_peers.lock().unwrap_or_else(|e| e.into_inner()).insert(peer.to_string());
+    state.session_peers.lock().unwrap_or_else(|e| e.into_inner()).insert(peer.to_string(), entry.clone());
+    events.emit("peer-connected", entry);
+}
+
+/// Drops `peer` from the session, emitting `peer-disconnected` if it was a member.
+/// Returns the awareness removals the rest of the session should hear about.
+fn remove_session_peer(events: &Events, state: &PeerState, peer: &PeerId) -> Vec<AwarenessEntry> {
+    state.active_peers.lock().unwrap_or_else(|e| e.into_inner()).remove(&peer.to_string());
+    let removed = state.session_peers.lock().unwrap_or_else(|e| e.into_inner()).remove(&peer.to_string());
+    if let Some(entry) = removed {
+        events.emit("peer-disconnected", entry);
+    }
+
+    let gone = state.awareness.lock().unwrap_or_else(|e| e.into_inner()).remove_peer(peer);
+    if !gone.is_empty() {
+        events.emit("awareness-changed", AwarenessChangedEvent::from_entries(&gone));
+    }
+    gone
+}
+
+/// Re-reads the transport of a session member after its connections changed.
+fn refresh_session_peer(events: &Events, state: &PeerState, tracker: &PeerTracker, peer: &PeerId) {
+    let updated = {
+        let mut members = state.session_peers.lock().unwrap_or_else(|e| e.into_inner());
+        members.get_mut(&peer.to_string()).and_then(|entry| {
+            let transport = tracker.transport(peer);
+            (entry.transport != transport).then(|| {
+                entry.transport = transport;
+                entry.clone()
+            })
+        })
+    };
+    if let Some(entry) = updated {
+        events.emit("peer-transport-changed", entry);
+    }
+}
+
+pub async fn start_p2p_node(
+    events: Events,
+    app_data_dir: PathBuf,
+    state: PeerState, 
+    mut cmd_rx: Receiver<SwarmCommand>
+) -> Result<(), Box<dyn std::error::Error>> {
+    
+    fs::create_dir_all(&app_data_dir)?;
+    let identity_path = app_data_dir.join("peer_identity");
+
+    let keypair = if identity_path.exists() {
+        let bytes = fs::read(&identity_path)?;
+        identity::Keypair::from_protobuf_encoding(&bytes)?
+    } else {
+        let key = identity::Keypair::generate_ed25519();
+        fs::write(&identity_path, key.to_protobuf_encoding()?)?;
+        key
+    };
+
+    let local_peer_id = PeerId::from(keypair.public());
+    eprintln!("Local Peer ID: {}", local_peer_id);
+    *state.local_peer_id.lock().unwrap_or_else(|e| e.into_inner()) = Some(local_peer_id.to_string());
+    events.emit("local-peer-id", local_peer_id.to_string());
+
+    let network_config = NetworkConfig::load(&app_data_dir);
+    *state.network_config.lock().unwrap_or_else(|e| e.into_inner()) = network_config.clone();
+    *state.trust.lock().unwrap_or_else(|e| e.into_inner()) = TrustList::load(&app_data_dir);
+
+    // 1. Create Relay Client
+    let (relay_transport, relay_behaviour) = relay_client::new(local_peer_id);
+
+    // 2. Build Swarm
+    let mut swarm = SwarmBuilder::with_existing_identity(keypair)
+        .with_tokio()
+        .with_quic()
+        .with_other_transport(|key| {
+            let noise_config = noise::Config::new(key).expect("failed to create noise config");
+            let yamux_config = yamux::Config::default();
+
+            relay_transport
+                .upgrade(Version::V1)
+                .authenticate(noise_config)
+                .multiplex(yamux_config)
+        })? 
+        .with_dns()? 
+        .with_behaviour(|key| {
+            // Listed first so peers that speak both settle on the binary codec
+            let request_response = request_response::Behaviour::with_codec(
+                CollabCodec::new(network_config.compression),
+                [(BINARY_PROTOCOL, ProtocolSupport::Full), (JSON_PROTOCOL, ProtocolSupport::Full)],
+                request_response::Config::default()
+                    .with_request_timeout(Duration::from_secs(network_config.join_timeout_secs) + JOIN_TIMEOUT_GRACE)
+            );
+            
+            // Signed messages let subscribers verify who authored each update
+            let gossipsub_config = gossipsub::ConfigBuilder::default()
+                .validation_mode(gossipsub::ValidationMode::Strict)
+                .max_transmit_size(GOSSIP_MAX_TRANSMIT_SIZE)
+                .build()?;
+            let gossipsub = gossipsub::Behaviour::new(
+                gossipsub::MessageAuthenticity::Signed(key.clone()),
+                gossipsub_config,
+            )?;
+
+            let identify = identify::Behaviour::new(
+                identify::Config::new(format!("/my-editor/{}", PROTOCOL_VERSION), key.public())
+                    .with_agent_version(format!("my-editor/{}", APP_VERSION)),
+            );
+
+            Ok(MyBehaviour { 
+                request_response,
+                relay_client: relay_behaviour,
+                dcutr: dcutr::Behaviour::new(local_peer_id),
+                identify,
+                ping: ping::Behaviour::new(ping::Config::new()),
+                mdns: if network_config.mdns {
+                    Some(mdns::tokio::Behaviour::new(mdns::Config::default(), local_peer_id)?)
+                } else {
+                    None
+                }.into(),
+                gossipsub,
+                stream: libp2p_stream::Behaviour::new(),
+            })
+        })?
+        .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+        .build();
+
+    let transfer_control = swarm.behaviour().stream.new_control();
+    let incoming_transfers = transfer_control.clone().accept(TRANSFER_PROTOCOL)?;
+    tokio::spawn(serve_transfers(incoming_transfers, events.clone(), state.clone()));
+
+    // 3. Listen on Local Interface
+    for addr in &network_config.listen_addrs {
+        swarm.listen_on(addr.parse()?)?;
+    }
+
+    // 4. Bootstrap: Dial the configured relays. We only listen on a circuit
+    // once the connection to a relay succeeds.
+
+    let mut relays = RelayPool::new(&network_config);
+    if network_config.enabled_relays().is_empty() {
+        eprintln!("WARNING: No usable relay configured.");
+    }
+    for relay_addr in relays.next_to_dial(Instant::now()) {
+        eprintln!("Dialing Relay: {}", relay_addr);
+        if let Err(e) = swarm.dial(DialOpts::from(relay_addr)) {
+            eprintln!("Failed to dial relay: {}", e);
+        }
+    }
+
+    let mut current_host: Option<PeerId> = None;
+    let mut tracker = PeerTracker::default();
+    let mut updates = UpdateLog::new(local_peer_id);
+    // Gossipsub channel of the current session, if the host enabled one
+    let mut session_topic: Option<(String, gossipsub::Sha256Topic)> = None;
+    let mut heartbeat = tokio::time::interval(Duration::from_secs(3));
+    let mut awareness_timer = tokio::time::interval(Duration::from_secs(5));
+    // Discovered peers may start or stop hosting at any time
+    let mut discovery_timer = tokio::time::interval(DISCOVERY_REFRESH);
+    let mut pending_join: Option<PendingJoin> = None;
+    let mut joining_guests: HashMap<PeerId, JoiningGuest> = HashMap::new();
+    // Keys from password exchanges whose join has not arrived yet
+    let mut password_keys: HashMap<PeerId, SessionKey> = HashMap::new();
+    let mut password_guesses = GuessLimiter::default();
+    // Members told their session ended, disconnected once they acknowledge
+    let mut leaving: HashMap<PeerId, (request_response::OutboundRequestId, Instant)> = HashMap::new();
+    // Documents whose updates arrived sealed with a key we were not granted
+    // yet, with the newest such epoch
+    let mut missed_updates: HashMap<String, u32> = HashMap::new();
+
+    loop {
+        let classify = |peer: &PeerId| {
+            if Some(*peer) == current_host {
+                PeerRole::Host
+            } else if state.session_peers.lock().unwrap_or_else(|e| e.into_inner()).contains_key(&peer.to_string()) {
+                PeerRole::Guest
+            } else if relays.is_relay(peer) {
+                PeerRole::Relay
+            } else {
+                PeerRole::Unknown
+            }
+        };
+
+        tokio::select! {
+            _ = heartbeat.tick() => {
+                if let Some(host_id) = current_host {
+                    swarm.behaviour_mut().request_response.send_request(&host_id, AppRequest::Ping);
+                }
+                let now = Instant::now();
+                let expired: Vec<PeerId> = joining_guests.iter().filter(|(_, g)| g.expires <= now).map(|(p, _)| *p).collect();
+                for peer in expired {
+                    eprintln!("Join request from {} expired", peer);
+                    joining_guests.remove(&peer);
+                    refuse_join(&mut swarm, &state, &peer, Some("Nobody answered the join request in time".into()), true);
+                    events.emit("join-request-expired", peer.to_string());
+                }
+                let overdue: Vec<PeerId> = leaving.iter().filter(|(_, (_, deadline))| *deadline <= now).map(|(p, _)| *p).collect();
+                for peer in overdue {
+                    leaving.remove(&peer);
+                    let _ = swarm.disconnect_peer_id(peer);
+                }
+                for relay_addr in relays.next_to_dial(now) {
+                    eprintln!("Dialing Relay: {}", relay_addr);
+                    let _ = swarm.dial(DialOpts::from(relay_addr));
+                }
+            }
+
+            _ = discovery_timer.tick() => {
+                let discovered: Vec<PeerId> = state.discovered_peers.lock().unwrap_or_else(|e| e.into_inner())
+                    .keys()
+                    .filter_map(|id| id.parse().ok())
+                    .collect();
+                for peer_id in discovered {
+                    swarm.behaviour_mut().request_response.send_request(&peer_id, AppRequest::SessionInfo);
+                }
+            }
+
+            _ = awareness_timer.tick() => {
+                let now = Instant::now();
+                let (expired, renewed) = {
+                    let mut awareness = state.awareness.lock().unwrap_or_else(|e| e.into_inner());
+                    (awareness.expire(now), awareness.renew_local(now))
+                };
+                if !expired.is_empty() {
+                    events.emit("awareness-changed", AwarenessChangedEvent { updated: Vec::new(), removed: expired });
+                }
+                if !renewed.is_empty() {
+                    let topic = session_topic.as_ref().map(|(_, t)| t);
+                    broadcast(&mut swarm, &state, current_host, topic, None, GossipMessage::Awareness { entries: renewed });
+                }
+            }
+
+            Some(command) = cmd_rx.recv() => {
+                match command {
+                    SwarmCommand::Join { peer_id: peer_str, remote_addrs, profile, password, tree, reply } => {
+                        if pending_join.is_some() {
+                            let _ = reply.send(Err("A join request is already in progress".into()));
+                            continue;
+                        }
+                        if let Err(e) = profile.validate() {
+                            let _ = reply.send(Err(e));
+                            continue;
+                        }
+                        let peer = match peer_str.parse::<PeerId>() {
+                            Ok(peer) => peer,
+                            Err(e) => {
+                                let _ = reply.send(Err(format!("Invalid peer id '{}': {}", peer_str, e)));
+                                continue;
+                            }
+                        };
+
+                        let mut multiaddrs = Vec::new();
+                        for addr_str in remote_addrs {
+                            if let Ok(addr) = addr_str.parse::<Multiaddr>() {
+                                multiaddrs.push(addr);
+                            }
+                        }
+
+                        // Add a circuit address through every configured relay
+                        for circuit_addr in relays.circuit_addrs(peer) {
+                            eprintln!("(Join) Adding Relay Circuit Address: {}", circuit_addr);
+                            multiaddrs.push(circuit_addr);
+                        }
+
+                        let opts = DialOpts::peer_id(peer)
+                            .addresses(multiaddrs)
+                            .build();
+
+                        // Dial first; the request goes out once the connection is established
+                        if let Err(e) = swarm.dial(opts) {
+                            if !swarm.is_connected(&peer) {
+                                let _ = reply.send(Err(format!("Could not dial host: {}", e)));
+                                continue;
+                            }
+                        }
+
+                        let mut join = PendingJoin { host: peer, request: None, profile, password, exchange: None, auth: None, tree, reply };
+                        if swarm.is_connected(&peer) {
+                            eprintln!("Already connected to {}. Sending Join Request immediately.", peer);
+                            join.request = Some(swarm.behaviour_mut().request_response.send_request(&peer, join.first_request(&local_peer_id)));
+                        } else {
+                            eprintln!("Dialing {}. Request queued until connection established.", peer);
+                        }
+                        pending_join = Some(join);
+                    },
+                    SwarmCommand::AcceptJoin { peer_id, root, project, role, reply } => {
+                        if role == SessionRole::Host {
+                            let _ = reply.send(Err("A session has only one host".into()));
+                            continue;
+                        }
+                        let Ok(peer) = peer_id.parse::<PeerId>() else {
+                            let _ = reply.send(Err(format!("No pending join request from {}", peer_id)));
+                            continue;
+                        };
+                        let joining = joining_guests.remove(&peer);
+                        let admission = Admission { peer, root, project, role, joining };
+                        let _ = reply.send(accept_join(&mut swarm, &events, &state, &tracker, &mut session_topic, &local_peer_id, admission));
+                    },
+                    SwarmCommand::Sync { path, data, reply } => {
+                        if !can_edit_locally(&state) {
+                            let _ = reply.send(Err("Viewers cannot edit this session".into()));
+                            continue;
+                        }
+                        apply_to_store(&state, &path, &data);
+                        let (epoch, data) = seal(&state, format!("sync:{}", path), data);
+                        let message = GossipMessage::Sync { path, data, id: updates.next_id(), epoch };
+                        let topic = session_topic.as_ref().map(|(_, t)| t);
+                        broadcast(&mut swarm, &state, current_host, topic, None, message);
+                        let _ = reply.send(Ok(()));
+                    },
+                    SwarmCommand::RequestSync { path, reply } => {
+                        // Send what we already have so peers only answer with the missing updates
+                        let state_vector = state.documents.lock().unwrap_or_else(|e| e.into_inner()).state_vector(&path).unwrap_or_default();
+                        for peer in session_targets(&state, current_host, None) {
+                            swarm.behaviour_mut().request_response.send_request(
+                                &peer,
+                                AppRequest::SyncStep1 { path: path.clone(), state_vector: state_vector.clone() }
+                            );
+                        }
+                        let _ = reply.send(Ok(()));
+                    },
+                    SwarmCommand::FileContent { path, data, reply } => {
+                        if !can_edit_locally(&state) {
+                            let _ = reply.send(Err("Viewers cannot edit this session".into()));
+                            continue;
+                        }
+                        let id = updates.next_id();
+                        let (epoch, data) = seal(&state, format!("file:{}", path), data);
+                        for peer in session_targets(&state, current_host, None) {
+                            swarm.behaviour_mut().request_response.send_request(
+                                &peer,
+                                AppRequest::FileContent { path: path.clone(), data: data.clone(), id: id.clone(), epoch }
+                            );
+                        }
+                        let _ = reply.send(Ok(()));
+                    },
+                    SwarmCommand::SetAwareness { client_id, state: client_state, reply } => {
+                        let entry = state.awareness.lock().unwrap_or_else(|e| e.into_inner()).set_local(client_id, client_state);
+                        let topic = session_topic.as_ref().map(|(_, t)| t);
+                        broadcast(&mut swarm, &state, current_host, topic, None, GossipMessage::Awareness { entries: vec![entry] });
+                        let _ = reply.send(Ok(()));
+                    },
+                    SwarmCommand::Chat { text, reply } => {
+                        let message = GossipMessage::Chat { author: local_peer_id.to_string(), text, id: updates.next_id() };
+                        let topic = session_topic.as_ref().map(|(_, t)| t);
+                        broadcast(&mut swarm, &state, current_host, topic, None, message);
+                        let _ = reply.send(Ok(()));
+                    },
+                    SwarmCommand::CancelTransfer { peer_id, reply } => {
+                        if state.transfers.lock().unwrap_or_else(|e| e.into_inner()).cancel(&peer_id) {
+                            events.emit("transfer-cancelled", peer_id);
+                            let _ = reply.send(Ok(()));
+                        } else {
+                            let _ = reply.send(Err(format!("No transfer with {} in progress", peer_id)));
+                        }
+                    },
+                    SwarmCommand::RejectJoin { peer_id, reason, reply } => {
+                        let Ok(peer) = peer_id.parse::<PeerId>() else {
+                            let _ = reply.send(Err(format!("Invalid Peer ID: {}", peer_id)));
+                            continue;
+                        };
+                        joining_guests.remove(&peer);
+                        let reason = reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+                        let outcome = if refuse_join(&mut swarm, &state, &peer, reason, false) {
+                            Ok(())
+                        } else {
+                            Err(format!("No pending join request from {}", peer_id))
+                        };
+                        let _ = reply.send(outcome);
+                    },
+                    SwarmCommand::SetPeerRole { peer_id, role, reply } => {
+                        let Ok(peer) = peer_id.parse::<PeerId>() else {
+                            let _ = reply.send(Err(format!("Invalid Peer ID: {}", peer_id)));
+                            continue;
+                        };
+                        if role == SessionRole::Host {
+                            let _ = reply.send(Err("A session has only one host".into()));
+                            continue;
+                        }
+                        let updated = state.session_peers.lock().unwrap_or_else(|e| e.into_inner())
+                            .get_mut(&peer_id)
+                            .filter(|p| p.role == PeerRole::Guest)
+                            .map(|entry| {
+                                entry.session_role = role;
+                                entry.clone()
+                            });
+                        let Some(entry) = updated else {
+                            let _ = reply.send(Err(format!("{} is not a guest of our session", peer_id)));
+                            continue;
+                        };
+                        swarm.behaviour_mut().request_response.send_request(&peer, AppRequest::SetRole { role });
+                        events.emit("peer-role-changed", entry);
+                        let _ = reply.send(Ok(()));
+                    },
+                    SwarmCommand::Kick { peer_id, reason, ban, reply } => {
+                        let Ok(peer) = peer_id.parse::<PeerId>() else {
+                            let _ = reply.send(Err(format!("Invalid Peer ID: {}", peer_id)));
+                            continue;
+                        };
+                        if Some(peer) == current_host {
+                            let _ = reply.send(Err("Cannot remove the host of the session we joined".into()));
+                            continue;
+                        }
+                        let member = state.session_peers.lock().unwrap_or_else(|e| e.into_inner())
+                            .get(&peer_id)
+                            .filter(|p| p.role == PeerRole::Guest)
+                            .cloned();
+                        if ban {
+                            let note = member.as_ref().and_then(|m| m.profile.as_ref()).map(|p| p.display_name.clone()).unwrap_or_default();
+                            let mut trust = state.trust.lock().unwrap_or_else(|e| e.into_inner());
+                            let mut updated = trust.clone();
+                            if let Err(e) = updated.set(&peer_id, TrustLevel::Blocked, &note).and_then(|_| updated.save(&app_data_dir)) {
+                                let _ = reply.send(Err(format!("Failed to ban {}: {}", peer_id, e)));
+                                continue;
+                            }
+                            *trust = updated;
+                        } else if member.is_none() {
+                            let _ = reply.send(Err(format!("{} is not a guest of our session", peer_id)));
+                            continue;
+                        }
+
+                        if swarm.is_connected(&peer) {
+                            let reason = reason.filter(|r| !r.trim().is_empty()).unwrap_or_else(|| {
+                                if ban { "You were banned from the session" } else { "You were removed from the session" }.to_string()
+                            });
+                            let request = swarm.behaviour_mut().request_response.send_request(&peer, AppRequest::SessionEnded { reason });
+                            leaving.insert(peer, (request, Instant::now() + KICK_GRACE));
+                        }
+                        // Drop the member right away so nothing else reaches it
+                        state.transfers.lock().unwrap_or_else(|e| e.into_inner()).cancel(&peer_id);
+                        if joining_guests.remove(&peer).is_some() {
+                            refuse_join(&mut swarm, &state, &peer, Some("You were removed from the session".into()), false);
+                        }
+                        let gone = remove_session_peer(&events, &state, &peer);
+                        if member.is_some() {
+                            rotate_session_key(&mut swarm, &events, &state, current_host);
+                        }
+                        if !gone.is_empty() {
+                            let topic = session_topic.as_ref().map(|(_, t)| t);
+                            broadcast(&mut swarm, &state, current_host, topic, Some(peer), GossipMessage::Awareness { entries: gone });
+                        }
+                        eprintln!("{} {}", if ban { "Banned" } else { "Kicked" }, peer);
+                        events.emit("peer-kicked", PeerKickedEvent { peer_id, banned: ban });
+                        let _ = reply.send(Ok(()));
+                    },
+                    SwarmCommand::UpdateNetworkConfig { config, reply } => {
+                        let (dropped, listeners) = relays.reconfigure(&config);
+                        for listener in listeners {
+                            swarm.remove_listener(listener);
+                        }
+                        for relay_id in dropped {
+                            eprintln!("Relay {} removed from config. Disconnecting.", relay_id);
+                            let _ = swarm.disconnect_peer_id(relay_id);
+                        }
+                        for relay_addr in relays.next_to_dial(Instant::now()) {
+                            eprintln!("Dialing Relay: {}", relay_addr);
+                            let _ = swarm.dial(DialOpts::from(relay_addr));
+                        }
+                        let _ = reply.send(Ok(()));
+                    },
+                }
+            }
+            
+            event = swarm.select_next_some() => {
+                match event {
+                    // --- NEW: Handle Relay Connection ---
+                    SwarmEvent::ConnectionEstablished { peer_id, connection_id, endpoint, .. } => {
+                        tracker.on_established(peer_id, connection_id, &endpoint);
+                        refresh_session_peer(&events, &state, &tracker, &peer_id);
+                        eprintln!("Connection established with {} ({:?}, {:?})", peer_id, classify(&peer_id), tracker.transport(&peer_id));
+
+                        // If we just connected to a Relay, start listening on the circuit
+                        if let Some(listen_via_relay) = relays.on_connected(&peer_id) {
+                            eprintln!("Connected to Relay {}. Requesting reservation...", peer_id);
+                            match swarm.listen_on(listen_via_relay) {
+                                Ok(listener) => relays.set_listener(&peer_id, listener),
+                                Err(e) => eprintln!("Failed to request relay reservation: {}", e),
+                            }
+                        }
+
+                        // Send the queued Join Request now that we are connected to the Host
+                        if let Some(join) = pending_join.as_mut().filter(|j| j.host == peer_id && j.request.is_none()) {
+                            eprintln!("Connected to Host {}. Sending Join Request now.", peer_id);
+                            join.request = Some(swarm.behaviour_mut().request_response.send_request(&peer_id, join.first_request(&local_peer_id)));
+                        }
+                    },
+                    SwarmEvent::OutgoingConnectionError { peer_id, error, .. } => {
+                        if let Some(relay_id) = peer_id.filter(|id| relays.is_relay(id)) {
+                            eprintln!("Failed to connect to Relay {}: {:?}", relay_id, error);
+                            if let Some(listener) = relays.on_failed(&relay_id, Instant::now()) {
+                                swarm.remove_listener(listener);
+                            }
+                            // Fail over to the next relay in priority order
+                            for relay_addr in relays.next_to_dial(Instant::now()) {
+                                eprintln!("Dialing Relay: {}", relay_addr);
+                                let _ = swarm.dial(DialOpts::from(relay_addr));
+                            }
+                        }
+                        // Fail the pending join if the host cannot be reached
+                        if let Some(host) = peer_id.filter(|id| pending_join.as_ref().is_some_and(|j| j.host == *id && j.request.is_none())) {
+                            eprintln!("Failed to connect to Host {}: {:?}", host, error);
+                            if let Some(join) = pending_join.take() {
+                                let _ = join.reply.send(Err(format!("Could not reach host: {}", error)));
+                            }
+                        }
+                    },
+                    SwarmEvent::ConnectionClosed { peer_id, connection_id, .. } => {
+                        if !tracker.on_closed(peer_id, connection_id) {
+                            refresh_session_peer(&events, &state, &tracker, &peer_id);
+                            continue;
+                        }
+
+                        let role = classify(&peer_id);
+                        eprintln!("All connections to {} closed ({:?})", peer_id, role);
+                        password_keys.remove(&peer_id);
+                        if joining_guests.remove(&peer_id).is_some() {
+                            state.pending_invites.lock().unwrap_or_else(|e| e.into_inner()).remove(&peer_id.to_string());
+                            state.transfers.lock().unwrap_or_else(|e| e.into_inner()).take_baseline(&peer_id);
+                            events.emit("join-request-expired", peer_id.to_string());
+                        }
+                        if relays.is_relay(&peer_id) {
+                            eprintln!("Lost connection to Relay {}.", peer_id);
+                            if let Some(listener) = relays.on_failed(&peer_id, Instant::now()) {
+                                swarm.remove_listener(listener);
+                            }
+                        }
+                        if Some(peer_id) == current_host {
+                            current_host = None;
+                            leave_session(&mut swarm, &state, &mut session_topic);
+                            events.emit("host-disconnected", peer_id.to_string());
+                        }
+                        let gone = remove_session_peer(&events, &state, &peer_id);
+                        if role == PeerRole::Guest {
+                            rotate_session_key(&mut swarm, &events, &state, current_host);
+                        }
+                        if !gone.is_empty() {
+                            let topic = session_topic.as_ref().map(|(_, t)| t);
+                            broadcast(&mut swarm, &state, current_host, topic, Some(peer_id), GossipMessage::Awareness { entries: gone });
+                        }
+                    },
+                    // ------------------------------------
+
+                    SwarmEvent::NewListenAddr { address, .. } => {
+                        let addr_str = address.to_string();
+                        eprintln!("Listening on {}", addr_str);
+                        
+                        // Check if this is the relay circuit address
+                        if addr_str.contains("p2p-circuit") {
+                            eprintln!("RELAY RESERVATION SUCCESS! Reachable via Relay.");
+                        }
+
+                        state.local_addrs.lock().unwrap_or_else(|e| e.into_inner()).push(addr_str.clone());
+                        events.emit("new-listen-addr", addr_str.clone());
+                        
+                        if addr_str.contains("0.0.0.0") && !addr_str.contains("p2p-circuit") {
+                            if let Some(lan_ip) = get_local_ip() {
+                                let fixed_addr = addr_str.replace("0.0.0.0", &lan_ip.to_string());
+                                eprintln!("Announcing LAN Addr: {}", fixed_addr);
+                                
+                                state.local_addrs.lock().unwrap_or_else(|e| e.into_inner()).push(fixed_addr.clone());
+                                events.emit("new-listen-addr", fixed_addr);
+                            }
+                        }
+                    },
+                    SwarmEvent::Behaviour(MyBehaviourEvent::Gossipsub(gossipsub::Event::Message { message, .. })) => {
+                        // Strict validation guarantees a verified source on every message
+                        let Some(author) = message.source else { continue };
+                        let in_session = session_topic.as_ref().is_some_and(|(_, t)| t.hash() == message.topic);
+                        if !in_session {
+                            continue;
+                        }
+                        match serde_json::from_slice::<GossipMessage>(&message.data) {
+                            Ok(GossipMessage::Sync { path, data, id, epoch }) => {
+                                // Only the host knows every member's role, so guests take edits
+                                // from it alone and it re-publishes what members may send
+                                if current_host.is_some_and(|host| host != author) {
+                                    continue;
+                                }
+                                if !authorize_edit(&events, &author, member_role(&state, current_host, &author), "sync", &path) {
+                                    continue;
+                                }
+                                if !updates.first_seen(&id) {
+                                    continue;
+                                }
+                                let Some(plain) = open(&state, &author, format!("sync:{}", path), epoch, data.clone()) else {
+                                    note_missed_update(&state, current_host, epoch, &path, &mut missed_updates);
+                                    continue;
+                                };
+                                apply_to_store(&state, &path, &plain);
+                                if current_host.is_none() {
+                                    let topic = session_topic.as_ref().map(|(_, t)| t);
+                                    broadcast(&mut swarm, &state, current_host, topic, Some(author), GossipMessage::Sync { path: path.clone(), data, id, epoch });
+                                }
+                                events.emit("p2p-sync", SyncEvent { path, data: plain });
+                            },
+                            Ok(GossipMessage::Awareness { entries }) => {
+                                if !speaks_for_session(&state, current_host, &author) {
+                                    eprintln!("Dropping awareness from {}: not a member of the session", author);
+                                    continue;
+                                }
+                                let applied = apply_awareness(&state, current_host, author, entries);
+                                if !applied.is_empty() {
+                                    events.emit("awareness-changed", AwarenessChangedEvent::from_entries(&applied));
+                                    if current_host.is_none() {
+                                        let topic = session_topic.as_ref().map(|(_, t)| t);
+                                        broadcast(&mut swarm, &state, current_host, topic, Some(author), GossipMessage::Awareness { entries: applied });
+                                    }
+                                }
+                            },
+                            Ok(GossipMessage::Chat { author: claimed, text, id }) => {
+                                if !speaks_for_session(&state, current_host, &author) {
+                                    eprintln!("Dropping chat from {}: not a member of the session", author);
+                                    continue;
+                                }
+                                if !updates.first_seen(&id) {
+                                    continue;
+                                }
+                                // Members speak for themselves; the host names the members it relays
+                                let speaker = if current_host.is_none() { author.to_string() } else { claimed };
+                                if current_host.is_none() {
+                                    let topic = session_topic.as_ref().map(|(_, t)| t);
+                                    let message = GossipMessage::Chat { author: speaker.clone(), text: text.clone(), id };
+                                    broadcast(&mut swarm, &state, current_host, topic, Some(author), message);
+                                }
+                                let verified = speaker == author.to_string();
+                                events.emit("chat-message", ChatEvent { peer_id: speaker, text, verified });
+                            },
+                            Err(e) => eprintln!("Invalid gossip message from {}: {}", author, e),
+                        }
+                    },
+                    SwarmEvent::Behaviour(MyBehaviourEvent::Mdns(mdns::Event::Discovered(list))) => {
+                        let mut found: Vec<PeerId> = Vec::new();
+                        {
+                            let mut discovered = state.discovered_peers.lock().unwrap_or_else(|e| e.into_inner());
+                            for (peer_id, addr) in list {
+                                swarm.add_peer_address(peer_id, addr.clone());
+                                let entry = discovered.entry(peer_id.to_string()).or_insert_with(|| {
+                                    found.push(peer_id);
+                                    DiscoveredPeer { peer_id: peer_id.to_string(), addrs: Vec::new(), session: None }
+                                });
+                                if !entry.addrs.contains(&addr.to_string()) {
+                                    entry.addrs.push(addr.to_string());
+                                }
+                            }
+                        }
+                        for peer_id in found {
+                            eprintln!("mDNS discovered {}", peer_id);
+                            // Ask whether it is hosting; the answer fills in `session`.
+                            swarm.behaviour_mut().request_response.send_request(&peer_id, AppRequest::SessionInfo);
+                            let entry = state.discovered_peers.lock().unwrap_or_else(|e| e.into_inner()).get(&peer_id.to_string()).cloned();
+                            if let Some(entry) = entry {
+                                events.emit("peer-discovered", entry);
+                            }
+                        }
+                    },
+                    SwarmEvent::Behaviour(MyBehaviourEvent::Mdns(mdns::Event::Expired(list))) => {
+                        for (peer_id, _) in list {
+                            // mDNS expires addresses one by one; only drop the peer once none remain.
+                            if swarm.behaviour().mdns.as_ref().is_some_and(|m| m.discovered_nodes().any(|id| *id == peer_id)) {
+                                continue;
+                            }
+                            if state.discovered_peers.lock().unwrap_or_else(|e| e.into_inner()).remove(&peer_id.to_string()).is_some() {
+                                eprintln!("mDNS peer expired {}", peer_id);
+                                events.emit("peer-expired", peer_id.to_string());
+                            }
+                        }
+                    },
+                    SwarmEvent::Behaviour(MyBehaviourEvent::RelayClient(relay_client::Event::ReservationReqAccepted { .. })) => {
+                        eprintln!("Relay accepted our reservation! We are now reachable via the relay.");
+                    },
+                    SwarmEvent::Behaviour(MyBehaviourEvent::Dcutr(dcutr::Event { remote_peer_id, result: Ok(_) })) => {
+                        eprintln!("HOLE PUNCH SUCCESS! Connected directly to {}", remote_peer_id);
+                        tracker.on_hole_punched(remote_peer_id);
+                        refresh_session_peer(&events, &state, &tracker, &remote_peer_id);
+                    },
+                    SwarmEvent::Behaviour(MyBehaviourEvent::RequestResponse(request_response::Event::Message { 
+                        peer, message: request_response::Message::Request { request, channel, .. }, ..
+                    })) => {
+                        match request {
+                            AppRequest::Join { profile, tree, info, auth } => {
+                                let profile = profile.sanitized();
+                                eprintln!("Join Request from {}: {}", peer, profile.display_name);
+                                if state.trust.lock().unwrap_or_else(|e| e.into_inner()).is_blocked(&peer) {
+                                    eprintln!("Refusing join from blocked peer {}", peer);
+                                    let response = AppResponse::Join { accepted: false, topic: None, reason: Some("The host does not accept join requests from you".into()), info: Some(PeerInfo::local()), expired: false, password_required: false, session_key: None, role: SessionRole::default() };
+                                    let _ = swarm.behaviour_mut().request_response.send_response(channel, response);
+                                    let _ = swarm.disconnect_peer_id(peer);
+                                    events.emit("join-blocked", peer.to_string());
+                                    continue;
+                                }
+                                if let Err(reason) = PeerInfo::check_compatible(info.as_ref()) {
+                                    eprintln!("Refusing join from {}: {}", peer, reason);
+                                    let response = AppResponse::Join { accepted: false, topic: None, reason: Some(reason.clone()), info: Some(PeerInfo::local()), expired: false, password_required: false, session_key: None, role: SessionRole::default() };
+                                    let _ = swarm.behaviour_mut().request_response.send_response(channel, response);
+                                    events.emit("join-incompatible", JoinRefusedEvent { peer_id: peer.to_string(), reason });
+                                    continue;
+                                }
+                                let trusted = state.trust.lock().unwrap_or_else(|e| e.into_inner()).is_trusted(&peer);
+                                // Nothing reaches the user, let alone leaves the project, without the password
+                                if state.session_password.lock().unwrap_or_else(|e| e.into_inner()).is_some() {
+                                    let key = password_keys.remove(&peer);
+                                    let verified = matches!((&key, &auth), (Some(key), Some(tag)) if key.verify(Side::Guest, tag));
+                                    if !verified {
+                                        let locked = !trusted && password_guesses.is_locked(&peer, Instant::now());
+                                        let reason = if locked {
+                                            "Too many wrong password attempts, try again later"
+                                        } else if auth.is_none() {
+                                            "This session requires a password"
+                                        } else {
+                                            "Wrong session password"
+                                        };
+                                        eprintln!("Refusing join from {}: {}", peer, reason);
+                                        let response = AppResponse::Join { accepted: false, topic: None, reason: Some(reason.to_string()), info: Some(PeerInfo::local()), expired: false, password_required: !locked, session_key: None, role: SessionRole::default() };
+                                        let _ = swarm.behaviour_mut().request_response.send_response(channel, response);
+                                        if auth.is_some() || locked {
+                                            events.emit("join-auth-failed", JoinRefusedEvent { peer_id: peer.to_string(), reason: reason.to_string() });
+                                        }
+                                        continue;
+                                    }
+                                    password_guesses.succeeded(&peer);
+                                }
+                                let Some(info) = info else { continue };
+                                let timeout = state.network_config.lock().unwrap_or_else(|e| e.into_inner()).join_timeout_secs;
+                                state.transfers.lock().unwrap_or_else(|e| e.into_inner()).set_baseline(&peer, tree);
+                                state.pending_invites.lock().unwrap_or_else(|e| e.into_inner()).insert(peer.to_string(), channel);
+                                let request = JoinRequestEvent {
+                                    peer_id: peer.to_string(),
+                                    profile: profile.clone(),
+                                    app_version: info.app_version.clone(),
+                                };
+                                let joining = JoiningGuest { info, profile, expires: Instant::now() + Duration::from_secs(timeout) };
+
+                                // Trusted peers join whatever project we share or have open
+                                let root = state.shared_project.lock().unwrap_or_else(|e| e.into_inner()).clone()
+                                    .or_else(|| state.workspace.lock().unwrap_or_else(|e| e.into_inner()).root().map(Path::to_path_buf));
+                                if let Some(root) = root.filter(|_| trusted) {
+                                    eprintln!("Approving join from trusted peer {}", peer);
+                                    let project = root.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                                    let admission = Admission { peer, root, project, role: SessionRole::Editor, joining: Some(joining) };
+                                    match accept_join(&mut swarm, &events, &state, &tracker, &mut session_topic, &local_peer_id, admission) {
+                                        Ok(()) => events.emit("join-auto-approved", request),
+                                        Err(e) => eprintln!("Failed to approve trusted peer {}: {}", peer, e),
+                                    }
+                                    continue;
+                                }
+                                events.emit("join-requested", request);
+                                joining_guests.insert(peer, joining);
+                            },
+                            AppRequest::Authenticate { pake } => {
+                                if state.trust.lock().unwrap_or_else(|e| e.into_inner()).is_blocked(&peer) {
+                                    let _ = swarm.behaviour_mut().request_response.send_response(channel, AppResponse::Authenticate { pake: None, confirm: None });
+                                    let _ = swarm.disconnect_peer_id(peer);
+                                    continue;
+                                }
+                                let password = state.session_password.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                                let trusted = state.trust.lock().unwrap_or_else(|e| e.into_inner()).is_trusted(&peer);
+                                let mut response = AppResponse::Authenticate { pake: None, confirm: None };
+                                // Each exchange is one online guess, so they are what we ration;
+                                // peers we trust are not guessing
+                                if let Some(password) = password.filter(|_| trusted || password_guesses.try_guess(peer, Instant::now())) {
+                                    let (exchange, ours) = PasswordExchange::start(&password, &local_peer_id, &peer);
+                                    match exchange.finish(&pake) {
+                                        Ok(key) => {
+                                            response = AppResponse::Authenticate { pake: Some(ours), confirm: Some(key.confirmation(Side::Host)) };
+                                            password_keys.insert(peer, key);
+                                        }
+                                        Err(e) => eprintln!("Password exchange with {} failed: {}", peer, e),
+                                    }
+                                }
+                                let _ = swarm.behaviour_mut().request_response.send_response(channel, response);
+                            },
+                            AppRequest::Sync { path, data, id, epoch } => {
+                                let _ = swarm.behaviour_mut().request_response.send_response(channel, AppResponse::Ack);
+                                if !authorize_edit(&events, &peer, member_role(&state, current_host, &peer), "sync", &path) {
+                                    continue;
+                                }
+                                if !updates.first_seen(&id) {
+                                    continue;
+                                }
+                                let Some(plain) = open(&state, &peer, format!("sync:{}", path), epoch, data.clone()) else {
+                                    note_missed_update(&state, current_host, epoch, &path, &mut missed_updates);
+                                    continue;
+                                };
+                                apply_to_store(&state, &path, &plain);
+                                // Relay to every other member so guests of a star topology converge.
+                                // Guests only know the host, so this is a no-op for them.
+                                let id = if id.is_empty() { updates.next_id() } else { id };
+                                let message = GossipMessage::Sync { path: path.clone(), data, id, epoch };
+                                let topic = session_topic.as_ref().map(|(_, t)| t);
+                                broadcast(&mut swarm, &state, current_host, topic, Some(peer), message);
+                                events.emit("p2p-sync", SyncEvent { path, data: plain });
+                            },
+                            AppRequest::RequestSync { path } => {
+                                let _ = swarm.behaviour_mut().request_response.send_response(channel, AppResponse::Ack);
+                                if !authorize_read(&events, &peer, member_role(&state, current_host, &peer), &path) {
+                                    continue;
+                                }
+                                // Answer from the document store when we have the file, so this
+                                // works even if the webview is not running.
+                                let stored = state.documents.lock().unwrap_or_else(|e| e.into_inner()).diff(&path, &[]);
+                                match stored {
+                                    Ok(Some(data)) => {
+                                        let (epoch, data) = seal(&state, format!("sync:{}", path), data);
+                                        swarm.behaviour_mut().request_response.send_request(&peer, AppRequest::Sync { path, data, id: updates.next_id(), epoch });
+                                    },
+                                    _ => {
+                                        events.emit("sync-requested", SyncRequestEvent { path });
+                                    }
+                                }
+                            },
+                            AppRequest::FileContent { path, data, id, epoch } => {
+                                let _ = swarm.behaviour_mut().request_response.send_response(channel, AppResponse::Ack);
+                                if !authorize_edit(&events, &peer, member_role(&state, current_host, &peer), "file-content", &path) {
+                                    continue;
+                                }
+                                if !updates.first_seen(&id) {
+                                    continue;
+                                }
+                                let Some(plain) = open(&state, &peer, format!("file:{}", path), epoch, data.clone()) else { continue };
+                                let id = if id.is_empty() { updates.next_id() } else { id };
+                                for target in session_targets(&state, current_host, Some(peer)) {
+                                    swarm.behaviour_mut().request_response.send_request(
+                                        &target,
+                                        AppRequest::FileContent { path: path.clone(), data: data.clone(), id: id.clone(), epoch }
+                                    );
+                                }
+                                events.emit("p2p-file-content", FileContentEvent { path, data: plain });
+                            },
+                            AppRequest::SyncStep1 { path, state_vector } => {
+                                if !authorize_read(&events, &peer, member_role(&state, current_host, &peer), &path) {
+                                    let response = AppResponse::SyncStep2 { path, diff: None, state_vector: None, epoch: 0 };
+                                    let _ = swarm.behaviour_mut().request_response.send_response(channel, response);
+                                    continue;
+                                }
+                                let (diff, own_state_vector) = {
+                                    let docs = state.documents.lock().unwrap_or_else(|e| e.into_inner());
+                                    (docs.diff(&path, &state_vector), docs.state_vector(&path))
+                                };
+                                let diff = match diff {
+                                    Ok(diff) => diff,
+                                    Err(e) => {
+                                        eprintln!("{}", e);
+                                        None
+                                    }
+                                };
+                                let (epoch, diff) = match diff {
+                                    Some(diff) => {
+                                        let (epoch, diff) = seal(&state, format!("diff:{}", path), diff);
+                                        (epoch, Some(diff))
+                                    },
+                                    None => {
+                                        // Not in the store yet; the webview may still have it open.
+                                        events.emit("sync-requested", SyncRequestEvent { path: path.clone() });
+                                        (0, None)
+                                    }
+                                };
+                                let _ = swarm.behaviour_mut().request_response.send_response(
+                                    channel,
+                                    AppResponse::SyncStep2 { path, diff, state_vector: own_state_vector, epoch }
+                                );
+                            },
+                            AppRequest::Awareness { entries } => {
+                                let _ = swarm.behaviour_mut().request_response.send_response(channel, AppResponse::Ack);
+                                if !speaks_for_session(&state, current_host, &peer) {
+                                    eprintln!("Dropping awareness from {}: not a member of the session", peer);
+                                    continue;
+                                }
+                                let applied = apply_awareness(&state, current_host, peer, entries);
+                                if !applied.is_empty() {
+                                    events.emit("awareness-changed", AwarenessChangedEvent::from_entries(&applied));
+                                    // Relay to the rest of the session; stale entries were already filtered out
+                                    let topic = session_topic.as_ref().map(|(_, t)| t);
+                                    broadcast(&mut swarm, &state, current_host, topic, Some(peer), GossipMessage::Awareness { entries: applied });
+                                }
+                            },
+                            AppRequest::Chat { author, text, id } => {
+                                let _ = swarm.behaviour_mut().request_response.send_response(channel, AppResponse::Ack);
+                                if !speaks_for_session(&state, current_host, &peer) {
+                                    eprintln!("Dropping chat from {}: not a member of the session", peer);
+                                    continue;
+                                }
+                                if !updates.first_seen(&id) {
+                                    continue;
+                                }
+                                // Members speak for themselves; the host names the members it relays
+                                let author = if current_host.is_none() { peer.to_string() } else { author };
+                                let id = if id.is_empty() { updates.next_id() } else { id };
+                                let message = GossipMessage::Chat { author: author.clone(), text: text.clone(), id };
+                                let topic = session_topic.as_ref().map(|(_, t)| t);
+                                broadcast(&mut swarm, &state, current_host, topic, Some(peer), message);
+                                let verified = author == peer.to_string();
+                                events.emit("chat-message", ChatEvent { peer_id: author, text, verified });
+                            },
+                            AppRequest::SessionInfo => {
+                                // Guests never advertise; only a node without a host of its own can be joined.
+                                let session = if current_host.is_none() {
+                                    state.advertised_session.lock().unwrap_or_else(|e| e.into_inner()).clone()
+                                } else {
+                                    None
+                                };
+                                let _ = swarm.behaviour_mut().request_response.send_response(channel, AppResponse::SessionInfo { session });
+                            },
+                            AppRequest::SessionKey { grant } => {
+                                let _ = swarm.behaviour_mut().request_response.send_response(channel, AppResponse::Ack);
+                                if Some(peer) != current_host {
+                                    eprintln!("Ignoring session key from {}: not our host", peer);
+                                    continue;
+                                }
+                                let installed = {
+                                    let mut keys = state.session_keys.lock().unwrap_or_else(|e| e.into_inner());
+                                    keys.install(&grant).map(|()| keys.current().is_some_and(|c| c.epoch() == grant.epoch))
+                                };
+                                match installed {
+                                    Ok(true) => events.emit("session-key-rotated", grant.epoch),
+                                    Ok(false) => {},
+                                    Err(e) => {
+                                        eprintln!("Invalid session key from {}: {}", peer, e);
+                                        continue;
+                                    }
+                                }
+                                // Ask again for what the host sealed with this key before we held it
+                                let retry: Vec<String> = missed_updates.iter()
+                                    .filter(|(_, epoch)| **epoch <= grant.epoch)
+                                    .map(|(path, _)| path.clone())
+                                    .collect();
+                                for path in retry {
+                                    missed_updates.remove(&path);
+                                    let state_vector = state.documents.lock().unwrap_or_else(|e| e.into_inner()).state_vector(&path).unwrap_or_default();
+                                    swarm.behaviour_mut().request_response.send_request(&peer, AppRequest::SyncStep1 { path, state_vector });
+                                }
+                            },
+                            AppRequest::SetRole { role } => {
+                                let _ = swarm.behaviour_mut().request_response.send_response(channel, AppResponse::Ack);
+                                if Some(peer) != current_host || role == SessionRole::Host {
+                                    eprintln!("Ignoring role change from {}: not our host", peer);
+                                    continue;
+                                }
+                                eprintln!("Host changed our role to {:?}", role);
+                                *state.local_role.lock().unwrap_or_else(|e| e.into_inner()) = Some(role);
+                                events.emit("session-role-changed", role);
+                            },
+                            AppRequest::SessionEnded { reason } => {
+                                let _ = swarm.behaviour_mut().request_response.send_response(channel, AppResponse::Ack);
+                                if Some(peer) != current_host {
+                                    eprintln!("Ignoring session end from {}: not our host", peer);
+                                    continue;
+                                }
+                                eprintln!("Host removed us from the session: {}", reason);
+                                current_host = None;
+                                leave_session(&mut swarm, &state, &mut session_topic);
+                                remove_session_peer(&events, &state, &peer);
+                                events.emit("session-ended", SessionEndedEvent { peer_id: peer.to_string(), reason });
+                            },
+                            AppRequest::Ping => {
+                                let _ = swarm.behaviour_mut().request_response.send_response(channel, AppResponse::Pong);
+                            }
+                        }
+                    },
+                    SwarmEvent::Behaviour(MyBehaviourEvent::RequestResponse(request_response::Event::Message { 
+                        peer, message: request_response::Message::Response { request_id, response }, ..
+                    })) => {
+                        if leaving.get(&peer).is_some_and(|(request, _)| *request == request_id) {
+                            leaving.remove(&peer);
+                            let _ = swarm.disconnect_peer_id(peer);
+                        }
+                        match response {
+                            AppResponse::Join { accepted, topic, reason, info, expired, password_required, session_key, role } => {
+                                if !accepted && pending_join.as_ref().is_some_and(|j| j.host == peer) {
+                                    if expired {
+                                        events.emit("join-timed-out", peer.to_string());
+                                    } else if password_required {
+                                        let reason = reason.clone().unwrap_or_default();
+                                        events.emit("join-password-required", JoinRefusedEvent { peer_id: peer.to_string(), reason });
+                                    } else {
+                                        events.emit("join-rejected", JoinRejectedEvent { peer_id: peer.to_string(), reason: reason.clone() });
+                                    }
+                                }
+                                let outcome = match (accepted, reason) {
+                                    (true, _) => PeerInfo::check_compatible(info.as_ref())
+                                        .map_err(|e| format!("Incompatible host: {}", e))
+                                        .and_then(|_| {
+                                            let grant = session_key.ok_or_else(|| "The host did not share a session key".to_string())?;
+                                            let mut keys = state.session_keys.lock().unwrap_or_else(|e| e.into_inner());
+                                            keys.clear();
+                                            keys.install(&grant)
+                                        }),
+                                    (false, _) if expired => Err("The host did not answer the join request in time".to_string()),
+                                    (false, Some(reason)) => Err(format!("The host refused the join: {}", reason)),
+                                    (false, None) => Err("The host declined the join request".to_string()),
+                                };
+                                let joined = outcome.is_ok();
+                                if joined {
+                                    missed_updates.clear();
+                                }
+                                if let Err(e) = &outcome {
+                                    eprintln!("Join with {} failed: {}", peer, e);
+                                }
+                                if let Some(join) = pending_join.take_if(|j| j.host == peer) {
+                                    let _ = join.reply.send(outcome);
+                                }
+                                if accepted && !joined {
+                                    // The host counts us as a guest; dropping the connection undoes that
+                                    let _ = swarm.disconnect_peer_id(peer);
+                                }
+                                if joined {
+                                    eprintln!("Joined session with {} as {:?}", peer, role);
+                                    current_host = Some(peer); 
+                                    *state.local_role.lock().unwrap_or_else(|e| e.into_inner()) = Some(role);
+                                    add_session_peer(&events, &state, &tracker, peer, SessionRole::Host, info.as_ref(), None);
+
+                                    if let Some(name) = topic {
+                                        let topic = topic_for_session(&name);
+                                        match swarm.behaviour_mut().gossipsub.subscribe(&topic) {
+                                            Ok(_) => session_topic = Some((name, topic)),
+                                            Err(e) => eprintln!("Failed to join session topic: {:?}", e),
+                                        }
+                                    }
+                                    
+                                    events.emit("join-accepted", peer.to_string());
+                                    let staging = transfer::staging_dir(&app_data_dir, &peer);
+                                    fetch_project(transfer_control.clone(), peer, &staging, &events, &state);
+                                }
+                            },
+                            AppResponse::Authenticate { pake, confirm } => {
+                                let Some(join) = pending_join.as_mut().filter(|j| j.host == peer) else { continue };
+                                let Some(exchange) = join.exchange.take() else { continue };
+                                // Without a host message the session has no password (or we are
+                                // locked out, which the join answer will say)
+                                let verified = match (pake, confirm) {
+                                    (Some(pake), Some(confirm)) => exchange.finish(&pake).ok()
+                                        .filter(|key| key.verify(Side::Host, &confirm))
+                                        .map(|key| Some(key.confirmation(Side::Guest))),
+                                    _ => Some(None),
+                                };
+                                match verified {
+                                    Some(auth) => {
+                                        join.auth = auth;
+                                        join.request = Some(swarm.behaviour_mut().request_response.send_request(&peer, join.request()));
+                                    }
+                                    None => {
+                                        let reason = "Wrong session password".to_string();
+                                        eprintln!("Join with {} failed: {}", peer, reason);
+                                        events.emit("join-password-required", JoinRefusedEvent { peer_id: peer.to_string(), reason: reason.clone() });
+                                        if let Some(join) = pending_join.take() {
+                                            let _ = join.reply.send(Err(reason));
+                                        }
+                                    }
+                                }
+                            },
+                            AppResponse::SyncStep2 { path, diff, state_vector, epoch } => {
+                                // Push back whatever the responder is missing from us
+                                if let Some(state_vector) = state_vector.filter(|_| can_edit_locally(&state)) {
+                                    let ours = state.documents.lock().unwrap_or_else(|e| e.into_inner()).diff(&path, &state_vector);
+                                    if let Ok(Some(data)) = ours {
+                                        if !is_empty_update(&data) {
+                                            let (epoch, data) = seal(&state, format!("sync:{}", path), data);
+                                            swarm.behaviour_mut().request_response.send_request(&peer, AppRequest::Sync { path: path.clone(), data, id: updates.next_id(), epoch });
+                                        }
+                                    }
+                                }
+                                let diff = diff
+                                    .filter(|_| authorize_edit(&events, &peer, member_role(&state, current_host, &peer), "sync", &path))
+                                    .map(|d| open(&state, &peer, format!("diff:{}", path), epoch, d));
+                                if diff.as_ref().is_some_and(Option::is_none) {
+                                    note_missed_update(&state, current_host, epoch, &path, &mut missed_updates);
+                                }
+                                let diff = diff.flatten();
+                                if let Some(data) = diff.filter(|d| !is_empty_update(d)) {
+                                    apply_to_store(&state, &path, &data);
+                                    events.emit("p2p-sync", SyncEvent { path, data });
+                                }
+                            },
+                            AppResponse::SessionInfo { session } => {
+                                let updated = {
+                                    let mut discovered = state.discovered_peers.lock().unwrap_or_else(|e| e.into_inner());
+                                    discovered.get_mut(&peer.to_string())
+                                        .filter(|entry| entry.session != session)
+                                        .map(|entry| {
+                                            entry.session = session;
+                                            entry.clone()
+                                        })
+                                };
+                                if let Some(entry) = updated {
+                                    events.emit("peer-discovered", entry);
+                                }
+                            },
+                            _ => {}
+                        }
+                    },
+                    SwarmEvent::Behaviour(MyBehaviourEvent::RequestResponse(request_response::Event::OutboundFailure { 
+                        peer, request_id, error, ..
+                    })) => {
+                        let role = classify(&peer);
+                        if leaving.get(&peer).is_some_and(|(request, _)| *request == request_id) {
+                            leaving.remove(&peer);
+                            let _ = swarm.disconnect_peer_id(peer);
+                        }
+                        if let Some(join) = pending_join.take_if(|j| j.request == Some(request_id)) {
+                            if matches!(error, request_response::OutboundFailure::Timeout) {
+                                events.emit("join-timed-out", peer.to_string());
+                            }
+                            let _ = join.reply.send(Err(format!("Join request failed: {}", error)));
+                        }
+                        if Some(peer) == current_host {
+                            current_host = None;
+                            leave_session(&mut swarm, &state, &mut session_topic);
+                            events.emit("host-disconnected", peer.to_string());
+                        }
+                        let gone = remove_session_peer(&events, &state, &peer);
+                        if role == PeerRole::Guest {
+                            rotate_session_key(&mut swarm, &events, &state, current_host);
+                        }
+                        if !gone.is_empty() {
+                            let topic = session_topic.as_ref().map(|(_, t)| t);
+                            broadcast(&mut swarm, &state, current_host, topic, Some(peer), GossipMessage::Awareness { entries: gone });
+                        }
+                    },
+                    _ => {}
+                }
+            }
+        }
+    }
+}
\ No newline at end of file
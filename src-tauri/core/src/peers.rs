@@ -0,0 +1,117 @@
+use std::collections::{HashMap, HashSet};
+use libp2p::{PeerId, core::ConnectedPoint, core::multiaddr::Protocol, swarm::ConnectionId};
+use serde::{Deserialize, Serialize};
+use crate::protocol::JoinProfile;
+
+/// Role of a peer relative to the local node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PeerRole {
+    /// The host of the session we joined.
+    Host,
+    /// A guest that joined the session we are hosting.
+    Guest,
+    Relay,
+    /// Connected, but not part of the session (e.g. a peer that is only dialing us).
+    Unknown,
+}
+
+/// What a member may do in a session. The host hands out roles when accepting
+/// a join and enforces them on everything members send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionRole {
+    Host,
+    /// Edits documents like the host.
+    #[default]
+    Editor,
+    /// Follows along read-only; cursors and chat still work.
+    Viewer,
+}
+
+impl SessionRole {
+    pub fn can_edit(self) -> bool {
+        self != SessionRole::Viewer
+    }
+}
+
+impl std::str::FromStr for SessionRole {
+    type Err = String;
+
+    /// Parses a role the host can grant: `editor` or `viewer`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "editor" => Ok(SessionRole::Editor),
+            "viewer" => Ok(SessionRole::Viewer),
+            other => Err(format!("Unknown role '{}', expected 'editor' or 'viewer'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PeerTransport {
+    Direct,
+    Relayed,
+    HolePunched,
+}
+
+/// Payload of the `peer-connected` / `peer-disconnected` events and entry of
+/// `list_session_peers`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionPeer {
+    pub peer_id: String,
+    pub role: PeerRole,
+    pub session_role: SessionRole,
+    pub transport: PeerTransport,
+    /// my-editor version the peer reported when joining.
+    pub app_version: Option<String>,
+    /// Capabilities both we and the peer support.
+    pub capabilities: Vec<String>,
+    /// Profile the guest joined with; `None` for the host.
+    pub profile: Option<JoinProfile>,
+}
+
+/// Open connections per peer, used to tell when a peer is really gone and how
+/// we are talking to it.
+#[derive(Default)]
+pub struct PeerTracker {
+    connections: HashMap<PeerId, HashMap<ConnectionId, bool>>,
+    hole_punched: HashSet<PeerId>,
+}
+
+impl PeerTracker {
+    pub fn on_established(&mut self, peer: PeerId, connection: ConnectionId, endpoint: &ConnectedPoint) {
+        let relayed = endpoint.get_remote_address().iter().any(|p| p == Protocol::P2pCircuit);
+        self.connections.entry(peer).or_default().insert(connection, relayed);
+    }
+
+    /// Returns `true` when the last connection to `peer` was closed.
+    pub fn on_closed(&mut self, peer: PeerId, connection: ConnectionId) -> bool {
+        let Some(conns) = self.connections.get_mut(&peer) else { return false };
+        conns.remove(&connection);
+        if conns.is_empty() {
+            self.connections.remove(&peer);
+            self.hole_punched.remove(&peer);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn on_hole_punched(&mut self, peer: PeerId) {
+        self.hole_punched.insert(peer);
+    }
+
+    pub fn transport(&self, peer: &PeerId) -> PeerTransport {
+        let direct = self
+            .connections
+            .get(peer)
+            .is_some_and(|conns| conns.values().any(|relayed| !relayed));
+        match (direct, self.hole_punched.contains(peer)) {
+            (true, true) => PeerTransport::HolePunched,
+            (true, false) => PeerTransport::Direct,
+            (false, _) => PeerTransport::Relayed,
+        }
+    }
+}
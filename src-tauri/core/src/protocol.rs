@@ -0,0 +1,229 @@
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use crate::config::NetworkConfig;
+use crate::crypto::KeyGrant;
+use crate::merkle::ProjectTree;
+use crate::peers::SessionRole;
+use crate::version::PeerInfo;
+
+const DEFAULT_DISPLAY_NAME: &str = "Guest";
+const DEFAULT_COLOR: &str = "#89b4fa";
+const MAX_DISPLAY_NAME_LEN: usize = 64;
+const MAX_MESSAGE_LEN: usize = 500;
+const MAX_AVATAR_SIZE: usize = 64 * 1024;
+
+/// Who is asking to join, shown to the host in the approval prompt and the
+/// session member list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JoinProfile {
+    pub display_name: String,
+    /// Hex color such as `#89b4fa`, used for the user's cursor and avatar ring.
+    pub color: String,
+    /// Small image in any format the frontend can display.
+    #[serde(with = "serde_bytes")]
+    pub avatar: Option<Vec<u8>>,
+    /// Free-text note to the host, e.g. why the user wants to join.
+    pub message: String,
+}
+
+impl Default for JoinProfile {
+    fn default() -> Self {
+        Self {
+            display_name: DEFAULT_DISPLAY_NAME.to_string(),
+            color: DEFAULT_COLOR.to_string(),
+            avatar: None,
+            message: String::new(),
+        }
+    }
+}
+
+impl JoinProfile {
+    /// Checks a profile we are about to send.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.display_name.trim().is_empty() {
+            return Err("Display name must not be empty".into());
+        }
+        if self.display_name.chars().count() > MAX_DISPLAY_NAME_LEN {
+            return Err(format!("Display name is longer than {} characters", MAX_DISPLAY_NAME_LEN));
+        }
+        if !is_hex_color(&self.color) {
+            return Err(format!("Invalid color '{}', expected #rrggbb", self.color));
+        }
+        if self.avatar.as_ref().is_some_and(|a| a.len() > MAX_AVATAR_SIZE) {
+            return Err(format!("Avatar is larger than {} KiB", MAX_AVATAR_SIZE / 1024));
+        }
+        if self.message.chars().count() > MAX_MESSAGE_LEN {
+            return Err(format!("Message is longer than {} characters", MAX_MESSAGE_LEN));
+        }
+        Ok(())
+    }
+
+    /// Brings a profile received from a peer within limits instead of refusing
+    /// the join over cosmetics.
+    pub fn sanitized(self) -> Self {
+        let clean = |text: &str, max: usize| -> String {
+            text.trim().chars().filter(|c| !c.is_control() || *c == '\n').take(max).collect()
+        };
+        let display_name = clean(&self.display_name, MAX_DISPLAY_NAME_LEN);
+        Self {
+            display_name: if display_name.is_empty() { DEFAULT_DISPLAY_NAME.to_string() } else { display_name },
+            color: if is_hex_color(&self.color) { self.color } else { DEFAULT_COLOR.to_string() },
+            avatar: self.avatar.filter(|a| a.len() <= MAX_AVATAR_SIZE),
+            message: clean(&self.message, MAX_MESSAGE_LEN),
+        }
+    }
+}
+
+fn is_hex_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// One client's entry in a Yjs awareness update.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AwarenessEntry {
+    pub client_id: u64,
+    pub clock: u32,
+    /// JSON-encoded state, `None` once the client has gone away.
+    pub state: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AppRequest {
+    /// `tree` describes the copy of the project the guest already has, if any.
+    /// `info` is missing from peers that predate version negotiation. `auth`
+    /// is the guest's key confirmation from a preceding `Authenticate`.
+    Join {
+        #[serde(default)] profile: JoinProfile,
+        #[serde(default)] tree: Option<ProjectTree>,
+        #[serde(default)] info: Option<PeerInfo>,
+        #[serde(default, with = "serde_bytes")] auth: Option<Vec<u8>>,
+    },
+    /// The guest's SPAKE2 message for a password-protected session.
+    Authenticate { #[serde(with = "serde_bytes")] pake: Vec<u8> },
+    /// `id` is assigned by the originating peer so relayed copies can be
+    /// recognised; it is empty when sent by peers that predate fan-out.
+    /// `data` is sealed with the session key of `epoch`, or plain if it is 0.
+    Sync {
+        path: String,
+        #[serde(with = "serde_bytes")] data: Vec<u8>,
+        #[serde(default)] id: String,
+        #[serde(default, skip_serializing_if = "is_plaintext")] epoch: u32,
+    },
+    FileContent {
+        path: String,
+        #[serde(with = "serde_bytes")] data: Vec<u8>,
+        #[serde(default)] id: String,
+        #[serde(default, skip_serializing_if = "is_plaintext")] epoch: u32,
+    },
+    RequestSync { path: String },
+    /// First half of the Yjs sync handshake: the requester's state vector for `path`.
+    SyncStep1 { path: String, #[serde(with = "serde_bytes")] state_vector: Vec<u8> },
+    Awareness { entries: Vec<AwarenessEntry> },
+    /// `author` is only trustworthy when it matches the sending peer; relayed
+    /// chat should travel over gossipsub where it is signed.
+    Chat { author: String, text: String, #[serde(default)] id: String },
+    SessionInfo,
+    /// A new session key from the host, sent after a member left.
+    SessionKey { grant: KeyGrant },
+    /// The host changed what we may do in the session.
+    SetRole { role: SessionRole },
+    /// The host removed us from its session and is about to disconnect.
+    SessionEnded { reason: String },
+    Ping,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AppResponse {
+    /// `topic` names the session's gossipsub channel when the host enabled one.
+    /// The project itself follows over the transfer protocol. `reason` says why
+    /// the join was refused, `info` describes the host. `expired` is set when
+    /// nobody answered the request in time.
+    Join {
+        accepted: bool,
+        #[serde(default)] topic: Option<String>,
+        #[serde(default)] reason: Option<String>,
+        #[serde(default)] info: Option<PeerInfo>,
+        #[serde(default)] expired: bool,
+        /// Set when the session needs a password the guest did not prove.
+        #[serde(default)] password_required: bool,
+        /// Key payloads of the session are sealed with, on acceptance.
+        #[serde(default)] session_key: Option<KeyGrant>,
+        /// What the guest may do once accepted.
+        #[serde(default)] role: SessionRole,
+    },
+    /// The host's SPAKE2 message and key confirmation; both `None` if the
+    /// session has no password.
+    Authenticate {
+        #[serde(default, with = "serde_bytes")] pake: Option<Vec<u8>>,
+        #[serde(default, with = "serde_bytes")] confirm: Option<Vec<u8>>,
+    },
+    SessionInfo { session: Option<String> },
+    /// Answer to `SyncStep1`: the updates the requester is missing, plus our own
+    /// state vector so it can send back what we lack. Both are `None` if we do
+    /// not hold the document. `diff` is sealed like `Sync` data.
+    SyncStep2 {
+        path: String,
+        #[serde(with = "serde_bytes")] diff: Option<Vec<u8>>,
+        #[serde(with = "serde_bytes")] state_vector: Option<Vec<u8>>,
+        #[serde(default, skip_serializing_if = "is_plaintext")] epoch: u32,
+    },
+    Ack,
+    Pong,
+}
+
+/// Epoch 0 marks an unencrypted payload; leaving it out keeps those messages
+/// byte-for-byte what they were before encryption.
+fn is_plaintext(epoch: &u32) -> bool {
+    *epoch == 0
+}
+
+/// Reply channel of a `SwarmCommand`; the swarm task answers once the command
+/// has actually succeeded or failed.
+pub type Reply<T = ()> = oneshot::Sender<Result<T, String>>;
+
+/// Commands sent from the Tauri commands to the swarm task.
+#[derive(Debug)]
+pub enum SwarmCommand {
+    /// Resolves when the host accepts or refuses, or the host cannot be reached.
+    /// `tree` is sent along so the host only transfers what changed.
+    /// `password` is needed for password-protected sessions.
+    Join { peer_id: String, remote_addrs: Vec<String>, profile: JoinProfile, password: Option<String>, tree: Option<ProjectTree>, reply: Reply },
+    /// Accepts a pending join and shares the project at `root` with the guest.
+    AcceptJoin { peer_id: String, root: PathBuf, project: String, role: SessionRole, reply: Reply },
+    /// Refuses a pending join; `reason` is shown to the guest.
+    RejectJoin { peer_id: String, reason: Option<String>, reply: Reply },
+    /// Changes what a guest of our session may do.
+    SetPeerRole { peer_id: String, role: SessionRole, reply: Reply },
+    /// Removes a guest from our session and disconnects it; `reason` is shown
+    /// to the guest. With `ban` the peer is also blocked from joining again.
+    Kick { peer_id: String, reason: Option<String>, ban: bool, reply: Reply },
+    Sync { path: String, data: Vec<u8>, reply: Reply },
+    RequestSync { path: String, reply: Reply },
+    FileContent { path: String, data: Vec<u8>, reply: Reply },
+    SetAwareness { client_id: u64, state: Option<String>, reply: Reply },
+    Chat { text: String, reply: Reply },
+    UpdateNetworkConfig { config: NetworkConfig, reply: Reply },
+    /// Aborts the project transfer with `peer_id`, in either direction.
+    CancelTransfer { peer_id: String, reply: Reply },
+}
+
+/// Session traffic published on the gossipsub topic. Each variant has a
+/// request-response twin used when there is no topic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GossipMessage {
+    Sync { path: String, #[serde(with = "serde_bytes")] data: Vec<u8>, id: String, #[serde(default)] epoch: u32 },
+    Awareness { entries: Vec<AwarenessEntry> },
+    Chat { author: String, text: String, id: String },
+}
+
+impl From<GossipMessage> for AppRequest {
+    fn from(message: GossipMessage) -> Self {
+        match message {
+            GossipMessage::Sync { path, data, id, epoch } => AppRequest::Sync { path, data, id, epoch },
+            GossipMessage::Awareness { entries } => AppRequest::Awareness { entries },
+            GossipMessage::Chat { author, text, id } => AppRequest::Chat { author, text, id },
+        }
+    }
+}
\ No newline at end of file
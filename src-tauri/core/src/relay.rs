@@ -0,0 +1,138 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use libp2p::{Multiaddr, PeerId, core::multiaddr::Protocol, core::transport::ListenerId};
+use crate::config::NetworkConfig;
+
+/// Wait before redialing a relay after its first failure; doubles with every
+/// further failure up to `MAX_BACKOFF`.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Consecutive failures of a relay and when it may be dialed again.
+struct Backoff {
+    failures: u32,
+    retry_at: Instant,
+}
+
+/// Tracks which configured relays we are dialing or holding a reservation on,
+/// and picks the next candidate when one of them fails.
+pub struct RelayPool {
+    candidates: Vec<(PeerId, Multiaddr)>,
+    max_reservations: usize,
+    dialing: HashSet<PeerId>,
+    reserved: HashMap<PeerId, Option<ListenerId>>,
+    failed: HashMap<PeerId, Backoff>,
+}
+
+impl RelayPool {
+    pub fn new(config: &NetworkConfig) -> Self {
+        Self {
+            candidates: config.enabled_relays(),
+            max_reservations: config.max_reservations.max(1),
+            dialing: HashSet::new(),
+            reserved: HashMap::new(),
+            failed: HashMap::new(),
+        }
+    }
+
+    pub fn is_relay(&self, peer: &PeerId) -> bool {
+        self.candidates.iter().any(|(id, _)| id == peer)
+    }
+
+    fn addr_of(&self, peer: &PeerId) -> Option<&Multiaddr> {
+        self.candidates.iter().find(|(id, _)| id == peer).map(|(_, addr)| addr)
+    }
+
+    /// Returns the relays that should be dialed to reach `max_reservations`,
+    /// marking them as in-flight. Relays that failed are skipped until their
+    /// backoff has passed.
+    pub fn next_to_dial(&mut self, now: Instant) -> Vec<Multiaddr> {
+        let mut out = Vec::new();
+        for (id, addr) in &self.candidates {
+            if self.dialing.len() + self.reserved.len() >= self.max_reservations {
+                break;
+            }
+            let backing_off = self.failed.get(id).is_some_and(|b| b.retry_at > now);
+            if self.dialing.contains(id) || self.reserved.contains_key(id) || backing_off {
+                continue;
+            }
+            self.dialing.insert(*id);
+            out.push(addr.clone());
+        }
+        out
+    }
+
+    /// Called on `ConnectionEstablished`. Returns the circuit address to listen on
+    /// if we still need a reservation on this relay.
+    pub fn on_connected(&mut self, peer: &PeerId) -> Option<Multiaddr> {
+        if self.reserved.contains_key(peer) || !self.is_relay(peer) {
+            return None;
+        }
+        self.dialing.remove(peer);
+        self.failed.remove(peer);
+        self.reserved.insert(*peer, None);
+        self.addr_of(peer).map(|addr| addr.clone().with(Protocol::P2pCircuit))
+    }
+
+    pub fn set_listener(&mut self, peer: &PeerId, listener: ListenerId) {
+        if let Some(slot) = self.reserved.get_mut(peer) {
+            *slot = Some(listener);
+        }
+    }
+
+    /// Called when dialing a relay fails or its last connection closes. Returns
+    /// the circuit listener that should be removed, if any.
+    pub fn on_failed(&mut self, peer: &PeerId, now: Instant) -> Option<ListenerId> {
+        if !self.is_relay(peer) {
+            return None;
+        }
+        self.dialing.remove(peer);
+        let failures = self.failed.get(peer).map_or(0, |b| b.failures).saturating_add(1);
+        let delay = INITIAL_BACKOFF.saturating_mul(2u32.saturating_pow(failures - 1)).min(MAX_BACKOFF);
+        self.failed.insert(*peer, Backoff { failures, retry_at: now + delay });
+        self.reserved.remove(peer).flatten()
+    }
+
+    /// Circuit addresses through which `target` might be reachable, with the
+    /// relays we are already connected to first.
+    pub fn circuit_addrs(&self, target: PeerId) -> Vec<Multiaddr> {
+        let mut relays: Vec<&(PeerId, Multiaddr)> = self.candidates.iter().collect();
+        relays.sort_by_key(|(id, _)| !self.reserved.contains_key(id));
+        relays
+            .into_iter()
+            .map(|(_, addr)| addr.clone().with(Protocol::P2pCircuit).with(Protocol::P2p(target)))
+            .collect()
+    }
+
+    /// Applies a new config. Returns the relays we should disconnect from and
+    /// the circuit listeners to remove because they are no longer enabled.
+    pub fn reconfigure(&mut self, config: &NetworkConfig) -> (Vec<PeerId>, Vec<ListenerId>) {
+        self.candidates = config.enabled_relays();
+        self.max_reservations = config.max_reservations.max(1);
+        self.failed.clear();
+
+        let candidates = &self.candidates;
+        let keep = |id: &PeerId| candidates.iter().any(|(c, _)| c == id);
+
+        let mut dropped = Vec::new();
+        let mut listeners = Vec::new();
+        self.reserved.retain(|id, listener| {
+            if keep(id) {
+                true
+            } else {
+                dropped.push(*id);
+                listeners.extend(listener.take());
+                false
+            }
+        });
+        self.dialing.retain(|id| {
+            if keep(id) {
+                true
+            } else {
+                dropped.push(*id);
+                false
+            }
+        });
+        (dropped, listeners)
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use libp2p::request_response::ResponseChannel;
+use crate::protocol::AppResponse;
+use crate::config::NetworkConfig;
+use crate::peers::{SessionPeer, SessionRole};
+use crate::documents::DocumentStore;
+use crate::awareness::AwarenessStore;
+use crate::transfer::TransferRegistry;
+use crate::crypto::SessionKeys;
+use crate::workspace::Workspace;
+use crate::trust::TrustList;
+use serde::Serialize;
+
+/// A peer found on the local network via mDNS.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveredPeer {
+    pub peer_id: String,
+    pub addrs: Vec<String>,
+    /// Project name the peer is hosting, as of its last answer to our periodic
+    /// `SessionInfo` query.
+    pub session: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct PeerState {
+    pub pending_invites: Arc<Mutex<HashMap<String, ResponseChannel<AppResponse>>>>,
+    pub active_peers: Arc<Mutex<HashSet<String>>>,
+    /// Host and guests of the current session, keyed by peer id.
+    pub session_peers: Arc<Mutex<HashMap<String, SessionPeer>>>,
+    /// Our own role in the current session; `None` outside of one.
+    pub local_role: Arc<Mutex<Option<SessionRole>>>,
+    pub local_peer_id: Arc<Mutex<Option<String>>>,
+    // ADD THIS: Store local addresses
+    pub local_addrs: Arc<Mutex<Vec<String>>>,
+    pub network_config: Arc<Mutex<NetworkConfig>>,
+    pub discovered_peers: Arc<Mutex<HashMap<String, DiscoveredPeer>>>,
+    /// Project name we answer `SessionInfo` queries with while hosting.
+    pub advertised_session: Arc<Mutex<Option<String>>>,
+    pub documents: Arc<Mutex<DocumentStore>>,
+    pub awareness: Arc<Mutex<AwarenessStore>>,
+    /// Project folder we share with guests while hosting.
+    pub shared_project: Arc<Mutex<Option<PathBuf>>>,
+    pub transfers: Arc<Mutex<TransferRegistry>>,
+    /// Password guests must prove they know before their join reaches the user.
+    pub session_password: Arc<Mutex<Option<String>>>,
+    /// Key of the session we host or joined, which `Sync`, `FileContent` and
+    /// project transfers are encrypted with.
+    pub session_keys: Arc<Mutex<SessionKeys>>,
+    /// Project and directories the webview's file commands are confined to.
+    pub workspace: Arc<Mutex<Workspace>>,
+    /// Peers whose joins are approved or refused without asking the user.
+    pub trust: Arc<Mutex<TrustList>>,
+}
+
+impl PeerState {
+    pub fn new() -> Self {
+        Self {
+            pending_invites: Arc::new(Mutex::new(HashMap::new())),
+            active_peers: Arc::new(Mutex::new(HashSet::new())),
+            session_peers: Arc::new(Mutex::new(HashMap::new())),
+            local_role: Arc::new(Mutex::new(None)),
+            local_peer_id: Arc::new(Mutex::new(None)),
+            // ADD THIS
+            local_addrs: Arc::new(Mutex::new(Vec::new())),
+            network_config: Arc::new(Mutex::new(NetworkConfig::default())),
+            discovered_peers: Arc::new(Mutex::new(HashMap::new())),
+            advertised_session: Arc::new(Mutex::new(None)),
+            documents: Arc::new(Mutex::new(DocumentStore::default())),
+            awareness: Arc::new(Mutex::new(AwarenessStore::default())),
+            shared_project: Arc::new(Mutex::new(None)),
+            transfers: Arc::new(Mutex::new(TransferRegistry::default())),
+            session_password: Arc::new(Mutex::new(None)),
+            session_keys: Arc::new(Mutex::new(SessionKeys::default())),
+            workspace: Arc::new(Mutex::new(Workspace::default())),
+            trust: Arc::new(Mutex::new(TrustList::default())),
+        }
+    }
+}
+impl Default for PeerState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
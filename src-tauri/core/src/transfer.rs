@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use futures::{AsyncReadExt, AsyncWriteExt};
+use libp2p::{PeerId, Stream, StreamProtocol};
+use serde::Serialize;
+use tokio::task::AbortHandle;
+use crate::crypto::{SessionCipher, SEAL_OVERHEAD};
+use crate::merkle::ProjectTree;
+use crate::files;
+
+/// Protocol the guest opens on the host to pull the project after joining.
+pub const TRANSFER_PROTOCOL: StreamProtocol = StreamProtocol::new("/my-editor/transfer/2.0.0");
+
+/// Size of the content frames; each write waits for the stream's flow
+/// control, so a slow guest slows the host down instead of piling up memory.
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Bytes between two `transfer-progress` events within a file.
+const PROGRESS_INTERVAL: u64 = 1024 * 1024;
+const MAX_PATH_LEN: usize = 4096;
+/// Received content, below the staging folder.
+pub const STAGED_FILES: &str = "files";
+/// Paths the host no longer has, as a JSON array, next to `STAGED_FILES`.
+pub const STAGED_DELETIONS: &str = "deleted.json";
+/// Received entries that were not written, as a JSON array of `SkippedEntry`.
+pub const STAGED_SKIPPED: &str = "skipped.json";
+
+/// Directories that are never part of a shared project.
+pub const SKIPPED_DIRS: [&str; 3] = [".git", "node_modules", "target"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Send,
+    Receive,
+}
+
+/// Payload of `transfer-progress`, `transfer-complete` and `transfer-failed`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferProgress {
+    pub peer_id: String,
+    pub direction: Direction,
+    pub files_done: u64,
+    pub files_total: u64,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    /// Files of the guest's copy that the host no longer has.
+    pub files_deleted: u64,
+    /// Set on `transfer-failed`.
+    pub error: Option<String>,
+}
+
+impl TransferProgress {
+    fn new(peer: PeerId, direction: Direction) -> Self {
+        Self { peer_id: peer.to_string(), direction, files_done: 0, files_total: 0, bytes_done: 0, bytes_total: 0, files_deleted: 0, error: None }
+    }
+
+    pub fn failed(peer: PeerId, direction: Direction, error: String) -> Self {
+        Self { error: Some(error), ..Self::new(peer, direction) }
+    }
+}
+
+/// Running transfers by remote peer id, so they can be cancelled, and the
+/// project trees guests sent with their join requests.
+#[derive(Default)]
+pub struct TransferRegistry {
+    active: HashMap<String, AbortHandle>,
+    baselines: HashMap<String, ProjectTree>,
+}
+
+impl TransferRegistry {
+    /// Tracks a new transfer with `peer`, aborting any earlier one.
+    pub fn insert(&mut self, peer: &PeerId, handle: AbortHandle) {
+        if let Some(previous) = self.active.insert(peer.to_string(), handle) {
+            previous.abort();
+        }
+    }
+
+    pub fn remove(&mut self, peer: &PeerId) {
+        self.active.remove(&peer.to_string());
+    }
+
+    /// Remembers what `peer` already has so only the difference is sent.
+    pub fn set_baseline(&mut self, peer: &PeerId, tree: Option<ProjectTree>) {
+        match tree {
+            Some(tree) => self.baselines.insert(peer.to_string(), tree),
+            None => self.baselines.remove(&peer.to_string()),
+        };
+    }
+
+    pub fn take_baseline(&mut self, peer: &PeerId) -> Option<ProjectTree> {
+        self.baselines.remove(&peer.to_string())
+    }
+
+    /// Aborts the transfer with `peer`; dropping the stream makes the other
+    /// side fail its transfer too. Returns whether one was running.
+    pub fn cancel(&mut self, peer: &str) -> bool {
+        match self.active.remove(peer) {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Where a guest stages the project received from `host` until the user
+/// picks a destination.
+pub fn staging_dir(app_data_dir: &Path, host: &PeerId) -> PathBuf {
+    app_data_dir.join("incoming").join(host.to_string())
+}
+
+/// Staging folder for a transfer id as reported to the UI (the host's peer id).
+pub fn staged_project(app_data_dir: &Path, transfer_id: &str) -> Result<PathBuf, String> {
+    let host = transfer_id.parse::<PeerId>().map_err(|e| format!("Invalid transfer id '{}': {}", transfer_id, e))?;
+    Ok(staging_dir(app_data_dir, &host))
+}
+
+/// Writes one frame of file content, sealed and length-prefixed when the
+/// session is encrypted. `offset` goes into the AAD so frames cannot be
+/// reordered or moved to another file.
+async fn write_chunk(stream: &mut Stream, cipher: Option<&SessionCipher>, path: &str, offset: u64, chunk: &[u8]) -> io::Result<()> {
+    match cipher {
+        Some(cipher) => {
+            let sealed = cipher.seal(format!("chunk:{}:{}", path, offset).as_bytes(), chunk);
+            stream.write_all(&(sealed.len() as u32).to_be_bytes()).await?;
+            stream.write_all(&sealed).await
+        }
+        None => stream.write_all(chunk).await,
+    }
+}
+
+/// Reads the frame `write_chunk` wrote for `buf.len()` bytes at `offset`.
+async fn read_chunk(stream: &mut Stream, cipher: Option<&SessionCipher>, path: &str, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+    let Some(cipher) = cipher else {
+        return stream.read_exact(buf).await;
+    };
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len).await?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len != buf.len() + SEAL_OVERHEAD {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Invalid encrypted frame length {}", len)));
+    }
+    let mut sealed = vec![0u8; len];
+    stream.read_exact(&mut sealed).await?;
+    let chunk = cipher
+        .open(format!("chunk:{}:{}", path, offset).as_bytes(), &sealed)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, e)))?;
+    buf.copy_from_slice(&chunk);
+    Ok(())
+}
+
+async fn write_path(stream: &mut Stream, path: &str) -> io::Result<()> {
+    stream.write_all(&(path.len() as u32).to_be_bytes()).await?;
+    stream.write_all(path.as_bytes()).await
+}
+
+async fn read_path(stream: &mut Stream) -> io::Result<String> {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len).await?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len == 0 || len > MAX_PATH_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Invalid path length {}", len)));
+    }
+    let mut path = vec![0u8; len];
+    stream.read_exact(&mut path).await?;
+    String::from_utf8(path).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Streams the project below `root`, or only what differs from `baseline`
+/// when the guest sent its tree: a header with the file and byte totals and
+/// the key epoch (0 if unencrypted), the deleted paths, then per file its
+/// path, size and content in `CHUNK_SIZE` frames sealed with `cipher`.
+pub async fn send_project(stream: &mut Stream, root: &Path, baseline: Option<ProjectTree>, cipher: Option<SessionCipher>, peer: PeerId, mut progress: impl FnMut(&TransferProgress)) -> io::Result<TransferProgress> {
+    let tree_root = root.to_path_buf();
+    let tree = tokio::task::spawn_blocking(move || ProjectTree::build(&tree_root)).await.map_err(io::Error::other)??;
+    let (changed, deleted) = match baseline {
+        Some(base) => {
+            let diff = tree.diff(&base);
+            (diff.changed, diff.deleted)
+        }
+        None => (tree.files(), Vec::new()),
+    };
+    let mut files = Vec::with_capacity(changed.len());
+    for relative in changed {
+        let size = tokio::fs::metadata(root.join(&relative)).await?.len();
+        files.push((relative, size));
+    }
+
+    let mut report = TransferProgress::new(peer, Direction::Send);
+    report.files_total = files.len() as u64;
+    report.bytes_total = files.iter().map(|(_, size)| size).sum();
+    report.files_deleted = deleted.len() as u64;
+
+    stream.write_all(&report.files_total.to_be_bytes()).await?;
+    stream.write_all(&report.bytes_total.to_be_bytes()).await?;
+    stream.write_all(&report.files_deleted.to_be_bytes()).await?;
+    stream.write_all(&cipher.as_ref().map_or(0, |c| c.epoch()).to_be_bytes()).await?;
+    for path in &deleted {
+        write_path(stream, path).await?;
+    }
+    progress(&report);
+
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    for (relative, size) in files {
+        write_path(stream, &relative).await?;
+        stream.write_all(&size.to_be_bytes()).await?;
+
+        // The announced size cannot be taken back, so a file that changes
+        // meanwhile fails the transfer instead of arriving truncated or padded
+        let changed = || io::Error::other(format!("{} changed while it was being sent", relative));
+        let mut file = tokio::fs::File::open(root.join(&relative)).await?;
+        let mut remaining = size;
+        let mut since_report = 0;
+        while remaining > 0 {
+            let want = remaining.min(CHUNK_SIZE as u64) as usize;
+            let chunk = tokio::io::AsyncReadExt::read(&mut file, &mut buf[..want]).await?;
+            if chunk == 0 {
+                return Err(changed());
+            }
+            write_chunk(stream, cipher.as_ref(), &relative, size - remaining, &buf[..chunk]).await?;
+            remaining -= chunk as u64;
+            report.bytes_done += chunk as u64;
+            since_report += chunk as u64;
+            if since_report >= PROGRESS_INTERVAL {
+                since_report = 0;
+                progress(&report);
+            }
+        }
+        if tokio::io::AsyncReadExt::read(&mut file, &mut buf[..1]).await? != 0 {
+            return Err(changed());
+        }
+        report.files_done += 1;
+        progress(&report);
+    }
+    stream.flush().await?;
+    stream.close().await?;
+    Ok(report)
+}
+
+/// Reads a project sent by `send_project` into `staging`, replacing whatever
+/// a previous transfer left there: content goes to `STAGED_FILES` as it
+/// arrives, deletions to `STAGED_DELETIONS`. A file whose path could land
+/// outside the project is read but not written, and listed in
+/// `STAGED_SKIPPED`. The content must be sealed with `cipher` if we have one.
+pub async fn receive_project(stream: &mut Stream, staging: &Path, cipher: Option<SessionCipher>, peer: PeerId, mut progress: impl FnMut(&TransferProgress)) -> io::Result<TransferProgress> {
+    if staging.exists() {
+        tokio::fs::remove_dir_all(staging).await?;
+    }
+    let dest = staging.join(STAGED_FILES);
+    tokio::fs::create_dir_all(&dest).await?;
+
+    let mut report = TransferProgress::new(peer, Direction::Receive);
+    let mut word = [0u8; 8];
+    stream.read_exact(&mut word).await?;
+    report.files_total = u64::from_be_bytes(word);
+    stream.read_exact(&mut word).await?;
+    report.bytes_total = u64::from_be_bytes(word);
+    stream.read_exact(&mut word).await?;
+    report.files_deleted = u64::from_be_bytes(word);
+    let mut epoch = [0u8; 4];
+    stream.read_exact(&mut epoch).await?;
+    let epoch = u32::from_be_bytes(epoch);
+    if report.files_total > files::MAX_IMPORT_FILES || report.bytes_total > files::MAX_IMPORT_BYTES || report.files_deleted > files::MAX_IMPORT_FILES {
+        let reason = format!("Refusing a project of {} files and {} bytes with {} deletions", report.files_total, report.bytes_total, report.files_deleted);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, reason));
+    }
+    if epoch != cipher.as_ref().map_or(0, |c| c.epoch()) {
+        let reason = match &cipher {
+            Some(_) if epoch == 0 => "Refusing an unencrypted project in an encrypted session".to_string(),
+            _ => format!("Project is sealed with session key epoch {}, which we do not hold", epoch),
+        };
+        return Err(io::Error::new(io::ErrorKind::InvalidData, reason));
+    }
+
+    // Checked when saving, which reports the unsafe ones
+    let mut deleted = Vec::new();
+    for _ in 0..report.files_deleted {
+        deleted.push(read_path(stream).await?);
+    }
+    let deleted = serde_json::to_vec(&deleted).map_err(io::Error::other)?;
+    tokio::fs::write(staging.join(STAGED_DELETIONS), deleted).await?;
+    progress(&report);
+
+    let mut skipped = Vec::new();
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    while report.files_done < report.files_total {
+        let relative = read_path(stream).await?;
+        stream.read_exact(&mut word).await?;
+        let size = u64::from_be_bytes(word);
+        if report.bytes_done + size > report.bytes_total {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} is larger than the announced project", relative)));
+        }
+
+        let mut file = match files::check_project_path(&relative) {
+            Ok(()) => {
+                let path = dest.join(&relative);
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                Some(tokio::fs::File::create(&path).await?)
+            }
+            Err(reason) => {
+                skipped.push(files::SkippedEntry { path: relative.clone(), reason });
+                None
+            }
+        };
+        let mut remaining = size;
+        let mut since_report = 0;
+        while remaining > 0 {
+            let want = remaining.min(CHUNK_SIZE as u64) as usize;
+            read_chunk(stream, cipher.as_ref(), &relative, size - remaining, &mut buf[..want]).await?;
+            if let Some(file) = &mut file {
+                tokio::io::AsyncWriteExt::write_all(file, &buf[..want]).await?;
+            }
+            remaining -= want as u64;
+            report.bytes_done += want as u64;
+            since_report += want as u64;
+            if since_report >= PROGRESS_INTERVAL {
+                since_report = 0;
+                progress(&report);
+            }
+        }
+        if let Some(file) = &mut file {
+            tokio::io::AsyncWriteExt::flush(file).await?;
+        }
+        report.files_done += 1;
+        progress(&report);
+    }
+    let skipped = serde_json::to_vec(&skipped).map_err(io::Error::other)?;
+    tokio::fs::write(staging.join(STAGED_SKIPPED), skipped).await?;
+    Ok(report)
+}
@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+
+const TRUST_LIST_FILE: &str = "trusted_peers.json";
+const MAX_NOTE_LEN: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrustLevel {
+    /// Joins without asking the user.
+    Trusted,
+    /// Refused and disconnected before the join reaches the user.
+    Blocked,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustEntry {
+    pub level: TrustLevel,
+    /// Who the peer is, e.g. a teammate's name.
+    #[serde(default)]
+    pub note: String,
+}
+
+/// A trust list entry as shown to the UI.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrustedPeer {
+    pub peer_id: String,
+    pub level: TrustLevel,
+    pub note: String,
+}
+
+/// Remembered join decisions, keyed by peer id and persisted in the app data dir.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrustList {
+    peers: BTreeMap<String, TrustEntry>,
+}
+
+impl TrustList {
+    /// Loads the list from the app data dir, starting empty if the file is
+    /// missing or unreadable.
+    pub fn load(app_data_dir: &Path) -> Self {
+        let path = app_data_dir.join(TRUST_LIST_FILE);
+        match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                eprintln!("Ignoring invalid trust list at {:?}: {}", path, e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self, app_data_dir: &Path) -> Result<(), String> {
+        fs::create_dir_all(app_data_dir).map_err(|e| e.to_string())?;
+        let bytes = serde_json::to_vec_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(app_data_dir.join(TRUST_LIST_FILE), bytes).map_err(|e| e.to_string())
+    }
+
+    pub fn level(&self, peer: &PeerId) -> Option<TrustLevel> {
+        self.peers.get(&peer.to_string()).map(|e| e.level)
+    }
+
+    pub fn is_trusted(&self, peer: &PeerId) -> bool {
+        self.level(peer) == Some(TrustLevel::Trusted)
+    }
+
+    pub fn is_blocked(&self, peer: &PeerId) -> bool {
+        self.level(peer) == Some(TrustLevel::Blocked)
+    }
+
+    /// Adds `peer_id` to the list or moves it to `level`, replacing its note.
+    pub fn set(&mut self, peer_id: &str, level: TrustLevel, note: &str) -> Result<(), String> {
+        let peer = peer_id.parse::<PeerId>().map_err(|e| format!("Invalid peer id '{}': {}", peer_id, e))?;
+        let note = note.trim();
+        if note.chars().count() > MAX_NOTE_LEN {
+            return Err(format!("Note is longer than {} characters", MAX_NOTE_LEN));
+        }
+        self.peers.insert(peer.to_string(), TrustEntry { level, note: note.to_string() });
+        Ok(())
+    }
+
+    /// Forgets `peer_id`; returns whether it was on the list.
+    pub fn remove(&mut self, peer_id: &str) -> bool {
+        self.peers.remove(peer_id).is_some()
+    }
+
+    /// Every entry, ordered by peer id.
+    pub fn entries(&self) -> Vec<TrustedPeer> {
+        self.peers.iter()
+            .map(|(peer_id, entry)| TrustedPeer { peer_id: peer_id.clone(), level: entry.level, note: entry.note.clone() })
+            .collect()
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::BTreeSet;
+use serde::{Deserialize, Serialize};
+
+/// Version of the my-editor build, sent so the UI can tell users what to update.
+pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Bumped whenever a change to the session protocol breaks older peers.
+/// 2: the project follows the join over the chunked transfer protocol.
+/// 3: session payloads and transfers are encrypted with a per-session key.
+pub const PROTOCOL_VERSION: u32 = 3;
+
+/// Oldest protocol version we can share a session with. Older peers may
+/// still reach us over the JSON codec, but only to be told to update.
+pub const MIN_PROTOCOL_VERSION: u32 = 3;
+
+/// Optional features a peer may support. Capabilities are plain strings so
+/// that names added by newer releases survive a round trip through older ones.
+pub mod capability {
+    /// Speaks `/collab/2.0.0` (CBOR, optionally compressed).
+    pub const BINARY_CODEC: &str = "binary-codec";
+    pub const AWARENESS: &str = "awareness";
+    pub const CHAT: &str = "chat";
+    /// Receives the project over the transfer stream protocol.
+    pub const CHUNKED_TRANSFER: &str = "chunked-transfer";
+    /// Sends a project tree so the host only transfers what changed.
+    pub const INCREMENTAL_TRANSFER: &str = "incremental-transfer";
+    pub const GOSSIPSUB: &str = "gossipsub";
+    /// Proves knowledge of the session password with a PAKE before joining.
+    pub const PASSWORD: &str = "password";
+    /// Seals document updates and transfers with the key granted at join.
+    pub const ENCRYPTION: &str = "encryption";
+}
+
+/// Capabilities a peer must have to join a session at all.
+const REQUIRED_CAPABILITIES: &[&str] = &[capability::CHUNKED_TRANSFER, capability::ENCRYPTION];
+
+/// What a peer runs and supports, exchanged in the join handshake.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerInfo {
+    pub app_version: String,
+    pub protocol_version: u32,
+    #[serde(default)]
+    pub capabilities: BTreeSet<String>,
+}
+
+impl PeerInfo {
+    /// This build's version and capabilities.
+    pub fn local() -> Self {
+        let capabilities = [
+            capability::BINARY_CODEC,
+            capability::AWARENESS,
+            capability::CHAT,
+            capability::CHUNKED_TRANSFER,
+            capability::INCREMENTAL_TRANSFER,
+            capability::GOSSIPSUB,
+            capability::PASSWORD,
+            capability::ENCRYPTION,
+        ];
+        Self {
+            app_version: APP_VERSION.to_string(),
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    /// Checks whether we can hold a session with the peer that sent `remote`;
+    /// `None` means it predates version negotiation, and with it encryption,
+    /// so it is refused.
+    pub fn check_compatible(remote: Option<&PeerInfo>) -> Result<(), String> {
+        let Some(remote) = remote else {
+            return Err(format!(
+                "Peer predates protocol version negotiation; my-editor with protocol {} or newer is required",
+                MIN_PROTOCOL_VERSION
+            ));
+        };
+        if remote.protocol_version < MIN_PROTOCOL_VERSION {
+            return Err(format!(
+                "my-editor {} speaks protocol {}, but protocol {} or newer is required",
+                remote.app_version, remote.protocol_version, MIN_PROTOCOL_VERSION
+            ));
+        }
+        let missing: Vec<&str> = REQUIRED_CAPABILITIES
+            .iter()
+            .copied()
+            .filter(|c| !remote.capabilities.contains(*c))
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "my-editor {} lacks required features: {}",
+                remote.app_version,
+                missing.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    /// Capabilities both sides support.
+    pub fn negotiate(&self, remote: &PeerInfo) -> Vec<String> {
+        self.capabilities.intersection(&remote.capabilities).cloned().collect()
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use serde::Serialize;
+
+/// Why a file command was refused.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum WorkspaceError {
+    /// A relative path was given before any project was opened.
+    NoWorkspace,
+    /// The path, after resolving `..` and symlinks, is neither inside the open
+    /// project nor in an allowed directory.
+    PathOutsideWorkspace { path: String },
+    /// The path is allowed but the operation on it failed.
+    Failed { message: String },
+}
+
+impl fmt::Display for WorkspaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkspaceError::NoWorkspace => write!(f, "No project is open"),
+            WorkspaceError::PathOutsideWorkspace { path } => write!(f, "Path is outside the workspace: {}", path),
+            WorkspaceError::Failed { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for WorkspaceError {}
+
+impl From<String> for WorkspaceError {
+    fn from(message: String) -> Self {
+        WorkspaceError::Failed { message }
+    }
+}
+
+/// The folders file commands may touch: the opened project plus an explicit
+/// allowlist (plugins, settings). Paths are checked after canonicalizing, so
+/// `..` and symlinks cannot lead out of them.
+#[derive(Debug, Clone, Default)]
+pub struct Workspace {
+    root: Option<PathBuf>,
+    allowed: Vec<PathBuf>,
+}
+
+impl Workspace {
+    /// Makes `root` the project relative paths resolve against, replacing the
+    /// previous one.
+    pub fn open(&mut self, root: &Path) -> Result<(), WorkspaceError> {
+        if !root.is_absolute() || !root.is_dir() {
+            return Err(format!("Not a project folder: {}", root.display()).into());
+        }
+        self.root = Some(root.to_path_buf());
+        Ok(())
+    }
+
+    pub fn close(&mut self) {
+        self.root = None;
+    }
+
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    /// Lets file commands reach `dir` even when no project is open. It does
+    /// not have to exist yet.
+    pub fn allow(&mut self, dir: PathBuf) {
+        if !self.allowed.contains(&dir) {
+            self.allowed.push(dir);
+        }
+    }
+
+    /// Turns a path from the webview into one file commands may use: relative
+    /// paths are taken from the project root, and the result must stay inside
+    /// the project or an allowed directory. The path is returned as given
+    /// (not canonicalized) so callers see the same prefix they sent.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, WorkspaceError> {
+        let outside = || WorkspaceError::PathOutsideWorkspace { path: path.to_string() };
+        let requested = Path::new(path);
+        if requested.components().any(|c| matches!(c, Component::ParentDir)) {
+            return Err(outside());
+        }
+        let full = if requested.is_absolute() {
+            requested.to_path_buf()
+        } else {
+            self.root.as_ref().ok_or(WorkspaceError::NoWorkspace)?.join(requested)
+        };
+
+        let canonical = canonicalize_lenient(&full).map_err(|_| outside())?;
+        let inside = self.root.iter()
+            .chain(&self.allowed)
+            .filter_map(|dir| canonicalize_lenient(dir).ok())
+            .any(|dir| canonical.starts_with(dir));
+        if inside { Ok(full) } else { Err(outside()) }
+    }
+}
+
+/// Canonicalizes the longest existing prefix of `path` and appends the rest,
+/// so files that are about to be created can be checked too. Dangling
+/// symlinks are refused since writing through them could land anywhere.
+fn canonicalize_lenient(path: &Path) -> io::Result<PathBuf> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        match existing.canonicalize() {
+            Ok(canonical) => return Ok(missing.iter().rev().fold(canonical, |p, name| p.join(name))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound && fs::symlink_metadata(existing).is_err() => {
+                let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else { return Err(e) };
+                missing.push(name);
+                existing = parent;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
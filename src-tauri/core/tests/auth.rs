@@ -0,0 +1,57 @@
+use std::time::Instant;
+use libp2p::PeerId;
+use my_editor_core::auth::{GuessLimiter, FREE_SESSION_GUESSES, GUESS_BACKOFF, GUESS_WINDOW, MAX_GUESS_BACKOFF, MAX_PASSWORD_ATTEMPTS};
+
+#[test]
+fn fresh_peer_ids_do_not_reset_the_guess_budget() {
+    let mut limiter = GuessLimiter::default();
+    let start = Instant::now();
+
+    let persistent = PeerId::random();
+    for _ in 0..MAX_PASSWORD_ATTEMPTS {
+        assert!(limiter.try_guess(persistent, start));
+    }
+    assert!(!limiter.try_guess(persistent, start));
+
+    // An attacker rotating identities gets slowed down for everyone, but
+    // nobody is locked out for good
+    for _ in MAX_PASSWORD_ATTEMPTS..FREE_SESSION_GUESSES {
+        assert!(limiter.try_guess(PeerId::random(), start));
+    }
+    assert!(limiter.try_guess(PeerId::random(), start));
+    let honest = PeerId::random();
+    assert!(limiter.is_locked(&honest, start));
+    assert!(limiter.try_guess(PeerId::random(), start + GUESS_BACKOFF));
+    assert!(!limiter.try_guess(honest, start + GUESS_BACKOFF * 2));
+    assert!(limiter.try_guess(honest, start + GUESS_BACKOFF * 3));
+
+    let mut now = start + GUESS_BACKOFF * 3;
+    for _ in 0..10 {
+        now += MAX_GUESS_BACKOFF;
+        assert!(limiter.try_guess(PeerId::random(), now), "backoff is capped");
+    }
+    assert!(!limiter.is_locked(&honest, now + GUESS_WINDOW));
+}
+
+#[test]
+fn a_verified_join_only_forgets_its_own_guesses() {
+    let mut limiter = GuessLimiter::default();
+    let start = Instant::now();
+
+    let honest = PeerId::random();
+    assert!(limiter.try_guess(honest, start));
+    for _ in 0..FREE_SESSION_GUESSES {
+        assert!(limiter.try_guess(PeerId::random(), start));
+    }
+    assert!(limiter.try_guess(PeerId::random(), start + GUESS_BACKOFF));
+
+    // The attacker's guesses alone still exceed the free budget
+    limiter.succeeded(&honest);
+    assert!(limiter.is_locked(&PeerId::random(), start + GUESS_BACKOFF * 2));
+
+    let other = PeerId::random();
+    assert!(limiter.try_guess(other, start + GUESS_BACKOFF * 3));
+    limiter.succeeded(&other);
+    assert!(limiter.is_locked(&PeerId::random(), start + GUESS_BACKOFF * 4));
+    assert!(!limiter.is_locked(&PeerId::random(), start + GUESS_BACKOFF * 5));
+}
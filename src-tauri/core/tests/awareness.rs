@@ -0,0 +1,58 @@
+use std::time::{Duration, Instant};
+use libp2p::PeerId;
+use my_editor_core::awareness::{AwarenessStore, OUTDATED_TIMEOUT};
+use my_editor_core::protocol::AwarenessEntry;
+
+fn entry(client_id: u64, clock: u32, state: Option<&str>) -> AwarenessEntry {
+    AwarenessEntry { client_id, clock, state: state.map(str::to_string) }
+}
+
+#[test]
+fn newer_clocks_win_and_removals_break_ties() {
+    let mut store = AwarenessStore::default();
+    let peer = PeerId::random();
+
+    assert_eq!(store.apply(peer, vec![entry(1, 3, Some("a"))]).len(), 1);
+    assert!(store.apply(peer, vec![entry(1, 2, Some("old"))]).is_empty());
+    assert!(store.apply(peer, vec![entry(1, 3, Some("same"))]).is_empty());
+    assert_eq!(store.apply(peer, vec![entry(1, 4, Some("b"))]), vec![entry(1, 4, Some("b"))]);
+    assert_eq!(store.apply(peer, vec![entry(1, 4, None)]), vec![entry(1, 4, None)]);
+    assert!(store.states().is_empty());
+
+    // Our own clients are never overwritten
+    store.set_local(9, Some("me".into()));
+    assert!(store.apply(peer, vec![entry(9, 100, None)]).is_empty());
+    assert_eq!(store.states(), vec![entry(9, 0, Some("me"))]);
+}
+
+#[test]
+fn peers_cannot_overwrite_each_others_clients() {
+    let mut store = AwarenessStore::default();
+    let (owner, intruder, host) = (PeerId::random(), PeerId::random(), PeerId::random());
+    store.apply(owner, vec![entry(1, 0, Some("cursor"))]);
+
+    assert!(store.apply(intruder, vec![entry(1, 50, Some("spoofed"))]).is_empty());
+    assert!(store.apply(intruder, vec![entry(1, 50, None)]).is_empty());
+    assert_eq!(store.states(), vec![entry(1, 0, Some("cursor"))]);
+
+    // The host relays removals of members that left
+    assert_eq!(store.apply_from_host(host, vec![entry(1, 1, None)]).len(), 1);
+    assert!(store.states().is_empty());
+}
+
+#[test]
+fn stale_remote_clients_expire_and_local_ones_renew() {
+    let mut store = AwarenessStore::default();
+    let peer = PeerId::random();
+    let start = Instant::now();
+    store.set_local(9, Some("me".into()));
+    store.apply(peer, vec![entry(1, 0, Some("a")), entry(2, 0, None)]);
+
+    assert!(store.expire(start + OUTDATED_TIMEOUT - Duration::from_secs(5)).is_empty());
+    let renewed = store.renew_local(start + OUTDATED_TIMEOUT);
+    assert_eq!(renewed, vec![entry(9, 1, Some("me"))]);
+    // Clients without a state are dropped silently
+    assert_eq!(store.expire(start + OUTDATED_TIMEOUT + Duration::from_secs(1)), vec![1]);
+    assert_eq!(store.states(), vec![entry(9, 1, Some("me"))]);
+    assert_eq!(store.apply(peer, vec![entry(2, 0, Some("back"))]).len(), 1, "expired clients can return");
+}
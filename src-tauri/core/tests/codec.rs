@@ -0,0 +1,62 @@
+mod common;
+
+use common::{legacy_request, TestPeer};
+use my_editor_core::codec::{decode_binary, encode_binary};
+use my_editor_core::protocol::{AppRequest, AppResponse, JoinProfile};
+
+#[test]
+fn binary_messages_round_trip_and_compress_large_payloads() {
+    let small = AppRequest::Sync { path: "a.md".into(), data: vec![1, 2, 3], id: "x".into(), epoch: 0 };
+    let encoded = encode_binary(&small, true).unwrap();
+    assert_eq!(encoded[0], 0, "small payloads are sent uncompressed");
+    assert_eq!(decode_binary::<AppRequest>(&encoded).unwrap(), small);
+
+    let large = AppRequest::FileContent { path: "b.md".into(), data: b"repetitive text ".repeat(4096), id: String::new(), epoch: 7 };
+    let plain = encode_binary(&large, false).unwrap();
+    let compressed = encode_binary(&large, true).unwrap();
+    assert_eq!(compressed[0], 1);
+    assert!(compressed.len() * 10 < plain.len(), "{} vs {}", compressed.len(), plain.len());
+    assert_eq!(decode_binary::<AppRequest>(&compressed).unwrap(), large);
+    assert_eq!(decode_binary::<AppRequest>(&plain).unwrap(), large);
+
+    // Byte strings, not arrays of integers
+    assert!(plain.len() < 4096 * 16 + 64);
+
+    let response = AppResponse::SyncStep2 { path: "a.md".into(), diff: Some(vec![0; 2048]), state_vector: None, epoch: 1 };
+    assert_eq!(decode_binary::<AppResponse>(&encode_binary(&response, true).unwrap()).unwrap(), response);
+    assert!(decode_binary::<AppRequest>(&[7, 0]).is_err());
+    assert!(decode_binary::<AppRequest>(&[]).is_err());
+
+    // Compressed bodies may not expand past the message limit
+    let mut bomb = vec![1u8];
+    bomb.extend(zstd::bulk::compress(&vec![0u8; 16 * 1024 * 1024 + 1], 3).unwrap());
+    assert!(bomb.len() < 4096);
+    assert!(decode_binary::<AppRequest>(&bomb).is_err());
+}
+
+#[test]
+fn json_encoding_is_unchanged_for_older_peers() {
+    let request = AppRequest::Sync { path: "a.md".into(), data: vec![1, 2], id: String::new(), epoch: 0 };
+    let json = serde_json::to_value(&request).unwrap();
+    assert_eq!(json, serde_json::json!({ "Sync": { "path": "a.md", "data": [1, 2], "id": "" } }));
+    let old: AppRequest = serde_json::from_str(r#"{"Sync":{"path":"a.md","data":[1,2]}}"#).unwrap();
+    assert_eq!(old, request);
+
+    let response = AppResponse::SyncStep2 { path: "a.md".into(), diff: None, state_vector: Some(vec![3]), epoch: 0 };
+    let json = serde_json::to_string(&response).unwrap();
+    assert_eq!(json, r#"{"SyncStep2":{"path":"a.md","diff":null,"state_vector":[3]}}"#);
+    assert_eq!(serde_json::from_str::<AppResponse>(&json).unwrap(), response);
+}
+
+#[tokio::test]
+async fn json_only_peers_are_answered_but_cannot_join() {
+    let node = TestPeer::spawn(&[]).await;
+    assert_eq!(legacy_request(&node, AppRequest::Ping).await, Ok(AppResponse::Pong));
+
+    // Compatibility stops at the codec: without version negotiation there is
+    // no session key, so the join is refused with a reason the peer can show
+    let join = AppRequest::Join { profile: JoinProfile::default(), tree: None, info: None, auth: None };
+    let Ok(AppResponse::Join { accepted, reason, .. }) = legacy_request(&node, join).await else { panic!("no join response") };
+    assert!(!accepted);
+    assert!(reason.unwrap().contains("protocol version negotiation"));
+}
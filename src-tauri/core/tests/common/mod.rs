@@ -0,0 +1,348 @@
+//! In-process session harness: a relay from `relay_server` plus any number of
+//! my-editor nodes on loopback QUIC, each with its own data dir and event log.
+
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use futures::StreamExt;
+use libp2p::{identity, swarm::SwarmEvent, Multiaddr, PeerId, SwarmBuilder};
+use libp2p::gossipsub;
+use libp2p::request_response::{self, ProtocolSupport};
+use my_editor_core::codec::JSON_PROTOCOL;
+use my_editor_core::protocol::{AppRequest, AppResponse, GossipMessage, JoinProfile};
+use my_editor_core::config::{NetworkConfig, RelayConfig};
+use my_editor_core::protocol::{Reply, SwarmCommand};
+use my_editor_core::merkle::ProjectTree;
+use my_editor_core::peers::SessionRole;
+use my_editor_core::transfer;
+use my_editor_core::{EventSink, Events, PeerState};
+use serde_json::Value;
+use tempfile::TempDir;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use yrs::{Doc, GetString, ReadTxn, StateVector, Text, Transact, Update};
+use yrs::updates::decoder::Decode;
+
+/// How long to wait for an expected event before failing the test.
+pub const EVENT_TIMEOUT: Duration = Duration::from_secs(15);
+
+const LOOPBACK_QUIC: &str = "/ip4/127.0.0.1/udp/0/quic-v1";
+
+/// Starts a relay on loopback and returns its full address (ending in `/p2p/<id>`).
+pub async fn spawn_relay() -> String {
+    let keypair = identity::Keypair::generate_ed25519();
+    let peer_id = PeerId::from(keypair.public());
+    let mut swarm = relay_server::build_swarm(keypair).expect("relay swarm");
+    swarm.listen_on(LOOPBACK_QUIC.parse().unwrap()).expect("relay listen");
+
+    let address = loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = swarm.select_next_some().await {
+            break address;
+        }
+    };
+    // Reservations only carry external addresses
+    swarm.add_external_address(address.clone());
+    tokio::spawn(relay_server::run(swarm));
+    format!("{}/p2p/{}", address, peer_id)
+}
+
+struct ChannelEvents(mpsc::UnboundedSender<(String, Value)>);
+
+impl EventSink for ChannelEvents {
+    fn emit(&self, event: &str, payload: Value) {
+        let _ = self.0.send((event.to_string(), payload));
+    }
+}
+
+pub struct TestPeer {
+    pub peer_id: String,
+    pub state: PeerState,
+    commands: mpsc::Sender<SwarmCommand>,
+    events: mpsc::UnboundedReceiver<(String, Value)>,
+    node: Node,
+    data_dir: TempDir,
+    /// Folder shared with guests when this peer hosts.
+    project: TempDir,
+}
+
+impl TestPeer {
+    /// Starts a node that listens on loopback, with mDNS off and the given relays.
+    pub async fn spawn(relays: &[String]) -> Self {
+        Self::spawn_with(relays, |_| {}).await
+    }
+
+    /// Like `spawn`, letting `configure` adjust the network config first.
+    pub async fn spawn_with(relays: &[String], configure: impl FnOnce(&mut NetworkConfig)) -> Self {
+        Self::spawn_in(TempDir::new().expect("data dir"), relays, configure).await
+    }
+
+    async fn spawn_in(data_dir: TempDir, relays: &[String], configure: impl FnOnce(&mut NetworkConfig)) -> Self {
+        let mut config = NetworkConfig {
+            relays: relays.iter().enumerate().map(|(i, address)| RelayConfig {
+                address: address.clone(),
+                priority: i as u32,
+                enabled: true,
+            }).collect(),
+            max_reservations: 1,
+            gossipsub: false,
+            listen_addrs: vec![LOOPBACK_QUIC.to_string()],
+            mdns: false,
+            compression: true,
+            join_timeout_secs: 120,
+        };
+        configure(&mut config);
+        config.save(data_dir.path()).expect("save config");
+
+        let state = PeerState::new();
+        let (commands, rx) = mpsc::channel(32);
+        let (event_tx, events) = mpsc::unbounded_channel();
+        let node_state = state.clone();
+        let dir = data_dir.path().to_path_buf();
+        let node = tokio::spawn(async move {
+            if let Err(e) = my_editor_core::start_p2p_node(Events::new(ChannelEvents(event_tx)), dir, node_state, rx).await {
+                eprintln!("P2P Network Error: {e}");
+            }
+        });
+
+        let project = TempDir::new().expect("project dir");
+        let mut peer = Self { peer_id: String::new(), state, commands, events, node: Node(node), data_dir, project };
+        peer.peer_id = peer.expect("local-peer-id").await.as_str().unwrap().to_string();
+        peer.expect_where("new-listen-addr", |addr| !addr.as_str().unwrap().contains("p2p-circuit")).await;
+        peer
+    }
+
+    /// Stops the node, dropping all of its connections.
+    pub fn shutdown(self) -> TempDir {
+        drop(self.node);
+        self.data_dir
+    }
+
+    /// Stops the node and starts it again with the same identity.
+    pub async fn restart(self, relays: &[String]) -> Self {
+        let data_dir = self.shutdown();
+        // Let the old swarm drop and release its connections
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        Self::spawn_in(data_dir, relays, |_| {}).await
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        self.data_dir.path()
+    }
+
+    /// Where this peer stages the project received from `host`.
+    pub fn staged_from(&self, host: &TestPeer) -> PathBuf {
+        transfer::staged_project(self.data_dir(), &host.peer_id).unwrap()
+    }
+
+    /// A file received from `host` that has not been saved yet.
+    pub fn staged_file(&self, host: &TestPeer, path: &str) -> PathBuf {
+        self.staged_from(host).join(transfer::STAGED_FILES).join(path)
+    }
+
+    /// Direct loopback addresses of this node, each ending in `/p2p/<id>`.
+    pub fn addrs(&self) -> Vec<String> {
+        self.state.local_addrs.lock().unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|a| !a.contains("p2p-circuit"))
+            .map(|a| format!("{}/p2p/{}", a, self.peer_id))
+            .collect()
+    }
+
+    pub async fn command<T>(&self, command: impl FnOnce(Reply<T>) -> SwarmCommand) -> Result<T, String> {
+        let (reply, outcome) = tokio::sync::oneshot::channel();
+        self.commands.send(command(reply)).await.map_err(|e| e.to_string())?;
+        outcome.await.map_err(|_| "Network task stopped before replying".to_string())?
+    }
+
+    /// Sends a join request to `host` without waiting for the answer, which
+    /// only comes once the host approves or declines.
+    pub fn join(&self, host: &TestPeer, remote_addrs: Vec<String>) -> JoinHandle<Result<(), String>> {
+        self.join_with(host, remote_addrs, None)
+    }
+
+    /// Like `join`, announcing a local copy of the project so only changes are sent.
+    pub fn join_with(&self, host: &TestPeer, remote_addrs: Vec<String>, tree: Option<ProjectTree>) -> JoinHandle<Result<(), String>> {
+        self.send_join(host, remote_addrs, JoinProfile::default(), None, tree)
+    }
+
+    /// Like `join`, introducing ourselves with `profile`.
+    pub fn join_as(&self, host: &TestPeer, remote_addrs: Vec<String>, profile: JoinProfile) -> JoinHandle<Result<(), String>> {
+        self.send_join(host, remote_addrs, profile, None, None)
+    }
+
+    /// Like `join`, for a session protected by `password`.
+    pub fn join_with_password(&self, host: &TestPeer, password: &str) -> JoinHandle<Result<(), String>> {
+        self.send_join(host, host.addrs(), JoinProfile::default(), Some(password.to_string()), None)
+    }
+
+    fn send_join(&self, host: &TestPeer, remote_addrs: Vec<String>, profile: JoinProfile, password: Option<String>, tree: Option<ProjectTree>) -> JoinHandle<Result<(), String>> {
+        let commands = self.commands.clone();
+        let peer_id = host.peer_id.clone();
+        tokio::spawn(async move {
+            let (reply, outcome) = tokio::sync::oneshot::channel();
+            commands.send(SwarmCommand::Join { peer_id, remote_addrs, profile, password, tree, reply }).await.map_err(|e| e.to_string())?;
+            outcome.await.map_err(|_| "Network task stopped before replying".to_string())?
+        })
+    }
+
+    pub fn project_dir(&self) -> &Path {
+        self.project.path()
+    }
+
+    /// Writes `files` into the shared project folder and accepts `guest` as an editor.
+    pub async fn approve(&self, guest: &str, files: &[(&str, &[u8])]) -> Result<(), String> {
+        self.approve_as(guest, SessionRole::Editor, files).await
+    }
+
+    /// Like `approve`, granting `guest` the given role.
+    pub async fn approve_as(&self, guest: &str, role: SessionRole, files: &[(&str, &[u8])]) -> Result<(), String> {
+        for (path, content) in files {
+            let path = self.project.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        let (peer_id, root) = (guest.to_string(), self.project.path().to_path_buf());
+        self.command(|reply| SwarmCommand::AcceptJoin { peer_id, root, project: "project".into(), role, reply }).await
+    }
+
+    /// Waits for the next `event`, skipping everything else.
+    pub async fn expect(&mut self, event: &str) -> Value {
+        self.expect_where(event, |_| true).await
+    }
+
+    /// Waits for an `event` whose payload satisfies `matches`.
+    pub async fn expect_where(&mut self, event: &str, matches: impl Fn(&Value) -> bool) -> Value {
+        let wait = async {
+            while let Some((name, payload)) = self.events.recv().await {
+                if name == event && matches(&payload) {
+                    return payload;
+                }
+            }
+            panic!("{} stopped while waiting for '{}'", self.peer_id, event);
+        };
+        match tokio::time::timeout(EVENT_TIMEOUT, wait).await {
+            Ok(payload) => payload,
+            Err(_) => panic!("{} timed out waiting for '{}'", self.peer_id, event),
+        }
+    }
+
+    /// Fails if `event` shows up within `window`.
+    pub async fn expect_none(&mut self, event: &str, window: Duration) {
+        let _ = tokio::time::timeout(window, async {
+            while let Some((name, payload)) = self.events.recv().await {
+                assert_ne!(name, event, "{} got unexpected '{}': {}", self.peer_id, event, payload);
+            }
+        }).await;
+    }
+
+    /// Text of the document at `path` as held by this node's store.
+    pub fn document_text(&self, path: &str) -> Option<String> {
+        let update = self.state.documents.lock().unwrap_or_else(|e| e.into_inner()).diff(path, &[]).ok()??;
+        let doc = Doc::new();
+        let text = doc.get_or_insert_text(TEXT_NAME);
+        doc.transact_mut().apply_update(Update::decode_v1(&update).ok()?).ok()?;
+        let txn = doc.transact();
+        Some(text.get_string(&txn))
+    }
+}
+
+/// Aborts the node task when the peer goes out of scope.
+struct Node(JoinHandle<()>);
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+const TEXT_NAME: &str = "content";
+
+/// A v1 Yjs update that inserts `content` into a fresh document.
+pub fn text_update(content: &str) -> Vec<u8> {
+    let doc = Doc::new();
+    let text = doc.get_or_insert_text(TEXT_NAME);
+    text.insert(&mut doc.transact_mut(), 0, content);
+    let txn = doc.transact();
+    txn.encode_state_as_update_v1(&StateVector::default())
+}
+
+pub fn bytes(value: &Value) -> Vec<u8> {
+    serde_json::from_value(value.clone()).expect("byte array payload")
+}
+
+/// Address of `relay` with a circuit to `peer`.
+pub fn circuit_addr(relay: &str, peer: &str) -> String {
+    let addr: Multiaddr = format!("{}/p2p-circuit/p2p/{}", relay, peer).parse().unwrap();
+    addr.to_string()
+}
+
+/// Sends `request` to `node` from a bare JSON-only peer, the way releases
+/// before `/collab/2.0.0` did, and returns the response.
+pub async fn legacy_request(node: &TestPeer, request: AppRequest) -> Result<AppResponse, String> {
+    let node_id: PeerId = node.peer_id.parse().unwrap();
+    let mut legacy = SwarmBuilder::with_new_identity()
+        .with_tokio()
+        .with_quic()
+        .with_behaviour(|_| request_response::json::Behaviour::<AppRequest, AppResponse>::new(
+            [(JSON_PROTOCOL, ProtocolSupport::Full)],
+            request_response::Config::default(),
+        ))
+        .unwrap()
+        .build();
+    legacy.add_peer_address(node_id, node.addrs()[0].parse().unwrap());
+    legacy.behaviour_mut().send_request(&node_id, request);
+
+    let wait = async {
+        loop {
+            match legacy.select_next_some().await {
+                SwarmEvent::Behaviour(request_response::Event::Message {
+                    message: request_response::Message::Response { response, .. }, ..
+                }) => return Ok(response),
+                SwarmEvent::Behaviour(request_response::Event::OutboundFailure { error, .. }) => return Err(error.to_string()),
+                _ => {}
+            }
+        }
+    };
+    tokio::time::timeout(EVENT_TIMEOUT, wait).await.expect("no response from node")
+}
+
+/// Subscribes a bare gossipsub peer that never joined to the topic of
+/// `host`'s session on `project`, and publishes `messages` there once the host
+/// is listening on it.
+pub async fn gossip_as_outsider(host: &TestPeer, project: &str, messages: Vec<GossipMessage>) {
+    let host_id: PeerId = host.peer_id.parse().unwrap();
+    let mut outsider = SwarmBuilder::with_new_identity()
+        .with_tokio()
+        .with_quic()
+        .with_behaviour(|key| gossipsub::Behaviour::<gossipsub::IdentityTransform>::new(
+            gossipsub::MessageAuthenticity::Signed(key.clone()),
+            gossipsub::ConfigBuilder::default().validation_mode(gossipsub::ValidationMode::Strict).build().unwrap(),
+        ).unwrap())
+        .unwrap()
+        .build();
+    let topic = gossipsub::Sha256Topic::new(format!("/my-editor/session/{}/{}", host.peer_id, project));
+    outsider.behaviour_mut().subscribe(&topic).unwrap();
+    outsider.dial(host.addrs()[0].parse::<Multiaddr>().unwrap()).unwrap();
+
+    let publish = async {
+        loop {
+            if let SwarmEvent::Behaviour(gossipsub::Event::Subscribed { peer_id, .. }) = outsider.select_next_some().await {
+                if peer_id == host_id {
+                    break;
+                }
+            }
+        }
+        for message in messages {
+            outsider.behaviour_mut().publish(topic.clone(), serde_json::to_vec(&message).unwrap()).unwrap();
+        }
+        // Stay connected while the messages go out
+        let drive = async {
+            loop {
+                outsider.select_next_some().await;
+            }
+        };
+        let _ = tokio::time::timeout(Duration::from_millis(300), drive).await;
+    };
+    tokio::time::timeout(EVENT_TIMEOUT, publish).await.expect("host never listened on the session topic");
+}
@@ -0,0 +1,47 @@
+use my_editor_core::crypto::{SessionKeys, SEAL_OVERHEAD};
+
+#[test]
+fn sealed_payloads_only_open_with_the_same_key_and_context() {
+    let mut keys = SessionKeys::default();
+    let grant = keys.rotate();
+    assert_eq!(grant.epoch, 1);
+    assert!(!format!("{:?}", grant).contains("key:"), "key material must not be logged");
+
+    let (epoch, sealed) = keys.seal(b"sync:a.md", b"update".to_vec());
+    assert_eq!(epoch, 1);
+    assert_eq!(sealed.len(), b"update".len() + SEAL_OVERHEAD);
+    assert_eq!(keys.open(epoch, b"sync:a.md", sealed.clone()).unwrap(), b"update");
+
+    let mut guest = SessionKeys::default();
+    guest.install(&grant).unwrap();
+    assert_eq!(guest.open(epoch, b"sync:a.md", sealed.clone()).unwrap(), b"update");
+
+    assert!(keys.open(epoch, b"sync:b.md", sealed.clone()).is_err(), "moved to another document");
+    let mut tampered = sealed.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert!(keys.open(epoch, b"sync:a.md", tampered).is_err());
+    assert!(keys.open(epoch, b"sync:a.md", sealed[..10].to_vec()).is_err());
+    assert!(keys.open(0, b"sync:a.md", b"plain".to_vec()).is_err(), "no downgrade to plaintext");
+    assert!(SessionKeys::default().open(epoch, b"sync:a.md", sealed).is_err());
+}
+
+#[test]
+fn rotation_keeps_the_old_key_briefly_and_never_goes_back() {
+    let mut keys = SessionKeys::default();
+    assert_eq!(keys.seal(b"sync:a.md", b"plain".to_vec()), (0, b"plain".to_vec()));
+
+    let first = keys.rotate();
+    let (old_epoch, in_flight) = keys.seal(b"sync:a.md", b"before".to_vec());
+    let second = keys.rotate();
+    assert_eq!(second.epoch, 2);
+    assert_eq!(keys.open(old_epoch, b"sync:a.md", in_flight).unwrap(), b"before");
+
+    // A delayed grant of the old key is ignored
+    keys.install(&first).unwrap();
+    assert_eq!(keys.current().unwrap().epoch(), 2);
+    assert!(!keys.is_behind(2));
+    assert!(keys.is_behind(3));
+
+    keys.clear();
+    assert!(keys.current().is_none());
+}
@@ -0,0 +1,47 @@
+use std::time::{Duration, Instant};
+use libp2p::{identity::Keypair, Multiaddr, PeerId};
+use my_editor_core::config::{NetworkConfig, RelayConfig};
+use my_editor_core::relay::RelayPool;
+
+fn relay_addr(peer: &PeerId) -> Multiaddr {
+    format!("/ip4/127.0.0.1/udp/4001/quic-v1/p2p/{}", peer).parse().unwrap()
+}
+
+#[test]
+fn failing_relays_back_off_instead_of_redialing() {
+    let (first, second) = (PeerId::from(Keypair::generate_ed25519().public()), PeerId::from(Keypair::generate_ed25519().public()));
+    let config = NetworkConfig {
+        relays: [first, second].iter().enumerate()
+            .map(|(i, p)| RelayConfig { address: relay_addr(p).to_string(), priority: i as u32, enabled: true })
+            .collect(),
+        max_reservations: 1,
+        ..NetworkConfig::default()
+    };
+    let mut pool = RelayPool::new(&config);
+    let start = Instant::now();
+    assert_eq!(pool.next_to_dial(start), vec![relay_addr(&first)]);
+    assert!(pool.next_to_dial(start).is_empty(), "already dialing");
+
+    // Fails over to the next relay, then nothing is left to dial right away
+    pool.on_failed(&first, start);
+    assert_eq!(pool.next_to_dial(start), vec![relay_addr(&second)]);
+    pool.on_failed(&second, start);
+    assert!(pool.next_to_dial(start).is_empty());
+    assert!(pool.next_to_dial(start + Duration::from_secs(1)).is_empty());
+    assert_eq!(pool.next_to_dial(start + Duration::from_secs(2)), vec![relay_addr(&first)]);
+
+    // Every further failure doubles the wait, up to five minutes
+    let mut pool = RelayPool::new(&NetworkConfig { relays: config.relays[..1].to_vec(), ..config.clone() });
+    let mut now = start;
+    for wait in [2, 4, 8, 16, 32, 64, 128, 256, 300, 300] {
+        assert_eq!(pool.next_to_dial(now), vec![relay_addr(&first)]);
+        pool.on_failed(&first, now);
+        assert!(pool.next_to_dial(now + Duration::from_secs(wait - 1)).is_empty(), "before {}s", wait);
+        now += Duration::from_secs(wait);
+    }
+
+    // A successful connection resets the backoff
+    pool.on_connected(&first);
+    pool.on_failed(&first, now);
+    assert_eq!(pool.next_to_dial(now + Duration::from_secs(2)), vec![relay_addr(&first)]);
+}
@@ -0,0 +1,637 @@
+mod common;
+
+use std::time::Duration;
+use common::{bytes, gossip_as_outsider, legacy_request, spawn_relay, text_update, TestPeer};
+use my_editor_core::peers::{PeerRole, PeerTransport, SessionRole};
+use my_editor_core::config::NetworkConfig;
+use my_editor_core::protocol::{AppRequest, AppResponse, AwarenessEntry, GossipMessage, JoinProfile, SwarmCommand};
+use my_editor_core::auth::{FREE_SESSION_GUESSES, MAX_PASSWORD_ATTEMPTS};
+use my_editor_core::trust::{TrustLevel, TrustList};
+use my_editor_core::version::{capability, PeerInfo, APP_VERSION, MIN_PROTOCOL_VERSION};
+
+/// Host plus `n` guests that joined directly over loopback.
+async fn session(n: usize) -> (TestPeer, Vec<TestPeer>) {
+    let mut host = TestPeer::spawn(&[]).await;
+    let mut guests = Vec::new();
+    for _ in 0..n {
+        let mut guest = TestPeer::spawn(&[]).await;
+        let join = guest.join(&host, host.addrs());
+        host.expect_where("join-requested", |p| p["peer_id"] == guest.peer_id.as_str()).await;
+        host.approve(&guest.peer_id, &[]).await.unwrap();
+        join.await.unwrap().unwrap();
+        guest.expect("join-accepted").await;
+        guests.push(guest);
+    }
+    (host, guests)
+}
+
+#[tokio::test]
+async fn join_waits_for_approval() {
+    let mut host = TestPeer::spawn(&[]).await;
+    let mut guest = TestPeer::spawn(&[]).await;
+
+    let join = guest.join(&host, host.addrs());
+    let requested = host.expect("join-requested").await;
+    assert_eq!(requested["peer_id"], guest.peer_id.as_str());
+    assert_eq!(requested["profile"]["display_name"], "Guest");
+    assert_eq!(requested["app_version"], APP_VERSION);
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(!join.is_finished(), "join resolved before the host answered");
+    assert!(guest.state.session_peers.lock().unwrap().is_empty());
+
+    host.approve(&guest.peer_id, &[("notes.md", b"hello")]).await.unwrap();
+    join.await.unwrap().unwrap();
+
+    let connected = guest.expect("peer-connected").await;
+    assert_eq!(connected["peer_id"], host.peer_id.as_str());
+    assert_eq!(connected["role"], "host");
+    assert_eq!(connected["app_version"], APP_VERSION);
+    assert!(connected["capabilities"].as_array().unwrap().iter().any(|c| c == capability::BINARY_CODEC));
+    let accepted = guest.expect("join-accepted").await;
+    assert_eq!(accepted, host.peer_id.as_str());
+    let done = guest.expect("transfer-complete").await;
+    assert_eq!(done["files_total"], 1);
+    assert_eq!(std::fs::read(guest.staged_file(&host, "notes.md")).unwrap(), b"hello");
+
+    let guest_entry = host.state.session_peers.lock().unwrap().get(&guest.peer_id).cloned().unwrap();
+    assert_eq!(guest_entry.role, PeerRole::Guest);
+    assert_eq!(guest_entry.transport, PeerTransport::Direct);
+}
+
+#[tokio::test]
+async fn incompatible_guests_are_refused_with_a_reason() {
+    let mut host = TestPeer::spawn(&[]).await;
+
+    let legacy = AppRequest::Join { profile: JoinProfile::default(), tree: None, info: None, auth: None };
+    let Ok(AppResponse::Join { accepted, reason, info, .. }) = legacy_request(&host, legacy).await else { panic!("no join response") };
+    assert!(!accepted);
+    assert!(reason.unwrap().contains("predates protocol version negotiation"));
+    assert_eq!(info, Some(PeerInfo::local()));
+    let refused = host.expect("join-incompatible").await;
+    assert!(refused["reason"].as_str().unwrap().contains("predates"));
+
+    let mut outdated = PeerInfo::local();
+    outdated.protocol_version = MIN_PROTOCOL_VERSION - 1;
+    let old = AppRequest::Join { profile: JoinProfile::default(), tree: None, info: Some(outdated), auth: None };
+    let Ok(AppResponse::Join { accepted, reason, .. }) = legacy_request(&host, old).await else { panic!("no join response") };
+    assert!(!accepted);
+    assert!(reason.unwrap().contains("or newer is required"));
+
+    let mut partial = PeerInfo::local();
+    partial.capabilities.remove(capability::CHUNKED_TRANSFER);
+    let Ok(AppResponse::Join { accepted, reason, .. }) = legacy_request(&host, AppRequest::Join { profile: JoinProfile::default(), tree: None, info: Some(partial), auth: None }).await else { panic!("no join response") };
+    assert!(!accepted);
+    assert!(reason.unwrap().contains(capability::CHUNKED_TRANSFER));
+
+    host.expect_none("join-requested", Duration::from_millis(300)).await;
+    assert!(host.state.pending_invites.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn join_profile_reaches_the_host_and_member_list() {
+    let mut host = TestPeer::spawn(&[]).await;
+    let guest = TestPeer::spawn(&[]).await;
+
+    let invalid = JoinProfile { color: "blue".into(), ..JoinProfile::default() };
+    let refused = guest.join_as(&host, host.addrs(), invalid).await.unwrap();
+    assert!(refused.unwrap_err().contains("Invalid color"));
+
+    let profile = JoinProfile {
+        display_name: "Ada".into(),
+        color: "#ff8800".into(),
+        avatar: Some(vec![0x89, b'P', b'N', b'G']),
+        message: "Reviewing chapter 2".into(),
+    };
+    let join = guest.join_as(&host, host.addrs(), profile.clone());
+    let requested = host.expect("join-requested").await;
+    assert_eq!(requested["profile"]["display_name"], "Ada");
+    assert_eq!(requested["profile"]["color"], "#ff8800");
+    assert_eq!(requested["profile"]["message"], "Reviewing chapter 2");
+    assert_eq!(bytes(&requested["profile"]["avatar"]), vec![0x89, b'P', b'N', b'G']);
+
+    host.approve(&guest.peer_id, &[]).await.unwrap();
+    join.await.unwrap().unwrap();
+    let connected = host.expect_where("peer-connected", |p| p["peer_id"] == guest.peer_id.as_str()).await;
+    assert_eq!(connected["profile"]["display_name"], "Ada");
+    let member = host.state.session_peers.lock().unwrap().get(&guest.peer_id).cloned().unwrap();
+    assert_eq!(member.profile, Some(profile));
+}
+
+#[test]
+fn received_profiles_are_brought_within_limits() {
+    let profile = JoinProfile {
+        display_name: format!("  {}\u{7}", "x".repeat(100)),
+        color: "javascript:alert(1)".into(),
+        avatar: Some(vec![0; 1024 * 1024]),
+        message: "hi".into(),
+    }.sanitized();
+    assert_eq!(profile.display_name, "x".repeat(64));
+    assert_eq!(profile.color, JoinProfile::default().color);
+    assert_eq!(profile.avatar, None);
+    assert_eq!(profile.message, "hi");
+    assert!(profile.validate().is_ok());
+    assert_eq!(JoinProfile { display_name: "   ".into(), ..JoinProfile::default() }.sanitized().display_name, "Guest");
+}
+
+#[tokio::test]
+async fn rejecting_a_join_tells_the_guest_why() {
+    let mut host = TestPeer::spawn(&[]).await;
+    let mut guest = TestPeer::spawn(&[]).await;
+
+    let join = guest.join(&host, host.addrs());
+    host.expect("join-requested").await;
+    let reason = Some("Session is full".to_string());
+    let peer_id = guest.peer_id.clone();
+    host.command(|reply| SwarmCommand::RejectJoin { peer_id, reason, reply }).await.unwrap();
+
+    assert!(join.await.unwrap().unwrap_err().contains("Session is full"));
+    let rejected = guest.expect("join-rejected").await;
+    assert_eq!(rejected["peer_id"], host.peer_id.as_str());
+    assert_eq!(rejected["reason"], "Session is full");
+    assert!(host.state.pending_invites.lock().unwrap().is_empty());
+    assert!(host.state.session_peers.lock().unwrap().is_empty());
+
+    let peer_id = guest.peer_id.clone();
+    assert!(host.command(|reply| SwarmCommand::RejectJoin { peer_id, reason: None, reply }).await.is_err());
+}
+
+#[tokio::test]
+async fn unanswered_join_requests_expire() {
+    let mut host = TestPeer::spawn_with(&[], |config| config.join_timeout_secs = 1).await;
+    let mut guest = TestPeer::spawn(&[]).await;
+
+    let join = guest.join(&host, host.addrs());
+    host.expect("join-requested").await;
+    let expired = host.expect("join-request-expired").await;
+    assert_eq!(expired, guest.peer_id.as_str());
+    assert_eq!(guest.expect("join-timed-out").await, host.peer_id.as_str());
+    assert!(join.await.unwrap().unwrap_err().contains("in time"));
+    assert!(host.state.pending_invites.lock().unwrap().is_empty());
+    assert!(host.approve(&guest.peer_id, &[]).await.is_err());
+}
+
+#[tokio::test]
+async fn requests_from_guests_that_leave_are_dropped() {
+    let mut host = TestPeer::spawn(&[]).await;
+    let guest = TestPeer::spawn(&[]).await;
+    let guest_id = guest.peer_id.clone();
+
+    let _join = guest.join(&host, host.addrs());
+    host.expect("join-requested").await;
+    guest.shutdown();
+
+    let expired = host.expect("join-request-expired").await;
+    assert_eq!(expired, guest_id.as_str());
+    assert!(host.state.pending_invites.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn password_protected_sessions_admit_only_guests_with_the_password() {
+    let mut host = TestPeer::spawn(&[]).await;
+    *host.state.session_password.lock().unwrap() = Some("correct horse".into());
+    let mut guest = TestPeer::spawn(&[]).await;
+
+    let no_password = guest.join(&host, host.addrs()).await.unwrap();
+    assert!(no_password.unwrap_err().contains("requires a password"));
+    let required = guest.expect("join-password-required").await;
+    assert_eq!(required["peer_id"], host.peer_id.as_str());
+
+    let wrong = guest.join_with_password(&host, "battery staple").await.unwrap();
+    assert!(wrong.unwrap_err().contains("Wrong session password"));
+    guest.expect("join-password-required").await;
+    host.expect_none("join-requested", Duration::from_millis(300)).await;
+
+    let join = guest.join_with_password(&host, "correct horse");
+    host.expect_where("join-requested", |p| p["peer_id"] == guest.peer_id.as_str()).await;
+    host.approve(&guest.peer_id, &[("notes.md", b"secret")]).await.unwrap();
+    join.await.unwrap().unwrap();
+    guest.expect("transfer-complete").await;
+}
+
+#[tokio::test]
+async fn skipping_the_password_exchange_is_refused() {
+    let mut host = TestPeer::spawn(&[]).await;
+    *host.state.session_password.lock().unwrap() = Some("correct horse".into());
+
+    let forged = AppRequest::Join { profile: JoinProfile::default(), tree: None, info: Some(PeerInfo::local()), auth: Some(vec![0; 32]) };
+    let Ok(AppResponse::Join { accepted, password_required, reason, .. }) = legacy_request(&host, forged).await else { panic!("no join response") };
+    assert!(!accepted);
+    assert!(password_required);
+    assert_eq!(reason.as_deref(), Some("Wrong session password"));
+    host.expect("join-auth-failed").await;
+    assert!(host.state.pending_invites.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn repeated_wrong_passwords_lock_the_guest_out() {
+    let mut host = TestPeer::spawn(&[]).await;
+    *host.state.session_password.lock().unwrap() = Some("correct horse".into());
+    let guest = TestPeer::spawn(&[]).await;
+
+    for _ in 0..MAX_PASSWORD_ATTEMPTS {
+        assert!(guest.join_with_password(&host, "guess").await.unwrap().is_err());
+    }
+    let locked = guest.join_with_password(&host, "correct horse").await.unwrap();
+    assert!(locked.unwrap_err().contains("Too many wrong password attempts"));
+    host.expect_none("join-requested", Duration::from_millis(300)).await;
+}
+
+#[tokio::test]
+async fn trusted_peers_are_not_slowed_down_by_strangers_guessing() {
+    let mut host = TestPeer::spawn(&[]).await;
+    *host.state.session_password.lock().unwrap() = Some("correct horse".into());
+    host.state.workspace.lock().unwrap().open(host.project_dir()).unwrap();
+    let guest = TestPeer::spawn(&[]).await;
+    host.state.trust.lock().unwrap().set(&guest.peer_id, TrustLevel::Trusted, "Ada").unwrap();
+
+    // Enough guesses from fresh identities to make everyone else wait
+    for _ in 0..FREE_SESSION_GUESSES + 6 {
+        legacy_request(&host, AppRequest::Authenticate { pake: vec![0; 33] }).await.unwrap();
+    }
+    guest.join_with_password(&host, "correct horse").await.unwrap().unwrap();
+    host.expect("join-auto-approved").await;
+}
+
+#[tokio::test]
+async fn a_password_is_harmless_when_the_session_has_none() {
+    let mut host = TestPeer::spawn(&[]).await;
+    let guest = TestPeer::spawn(&[]).await;
+    let join = guest.join_with_password(&host, "unused");
+    host.expect("join-requested").await;
+    host.approve(&guest.peer_id, &[]).await.unwrap();
+    join.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn approving_an_unknown_peer_fails() {
+    let host = TestPeer::spawn(&[]).await;
+    let stranger = TestPeer::spawn(&[]).await;
+    assert!(host.approve(&stranger.peer_id, &[]).await.is_err());
+}
+
+#[tokio::test]
+async fn host_sync_reaches_every_guest() {
+    let (host, mut guests) = session(2).await;
+
+    let data = text_update("from host");
+    let (path, update) = ("a.md".to_string(), data.clone());
+    host.command(|reply| SwarmCommand::Sync { path, data: update, reply }).await.unwrap();
+
+    for guest in &mut guests {
+        let sync = guest.expect("p2p-sync").await;
+        assert_eq!(sync["path"], "a.md");
+        assert_eq!(bytes(&sync["data"]), data);
+        assert_eq!(guest.document_text("a.md").as_deref(), Some("from host"));
+    }
+}
+
+#[tokio::test]
+async fn guest_sync_is_relayed_to_other_guests_once() {
+    let (mut host, mut guests) = session(2).await;
+
+    let data = text_update("from guest");
+    let (path, update) = ("b.md".to_string(), data.clone());
+    guests[0].command(|reply| SwarmCommand::Sync { path, data: update, reply }).await.unwrap();
+
+    let at_host = host.expect("p2p-sync").await;
+    assert_eq!(bytes(&at_host["data"]), data);
+    let at_other = guests[1].expect("p2p-sync").await;
+    assert_eq!(bytes(&at_other["data"]), data);
+    assert_eq!(guests[1].document_text("b.md").as_deref(), Some("from guest"));
+
+    // Neither the author nor the other guest sees the update a second time
+    guests[0].expect_none("p2p-sync", Duration::from_millis(500)).await;
+    guests[1].expect_none("p2p-sync", Duration::from_millis(500)).await;
+}
+
+#[tokio::test]
+async fn request_sync_is_answered_from_the_document_store() {
+    let (mut host, mut guests) = session(1).await;
+    let guest = &mut guests[0];
+
+    let (path, data) = ("c.md".to_string(), text_update("stored"));
+    host.command(|reply| SwarmCommand::Sync { path, data, reply }).await.unwrap();
+    guest.expect("p2p-sync").await;
+
+    // A second guest joins late and catches up with the state-vector handshake
+    let mut late = TestPeer::spawn(&[]).await;
+    let join = late.join(&host, host.addrs());
+    host.expect_where("join-requested", |p| p["peer_id"] == late.peer_id.as_str()).await;
+    host.approve(&late.peer_id, &[]).await.unwrap();
+    join.await.unwrap().unwrap();
+    assert_eq!(late.document_text("c.md"), None);
+
+    let path = "c.md".to_string();
+    late.command(|reply| SwarmCommand::RequestSync { path, reply }).await.unwrap();
+    let sync = late.expect_where("p2p-sync", |p| p["path"] == "c.md").await;
+    assert!(!bytes(&sync["data"]).is_empty());
+    assert_eq!(late.document_text("c.md").as_deref(), Some("stored"));
+}
+
+#[tokio::test]
+async fn request_sync_for_unknown_file_asks_the_host_frontend() {
+    let (mut host, guests) = session(1).await;
+
+    let path = "missing.md".to_string();
+    guests[0].command(|reply| SwarmCommand::RequestSync { path, reply }).await.unwrap();
+    let requested = host.expect("sync-requested").await;
+    assert_eq!(requested["path"], "missing.md");
+}
+
+#[tokio::test]
+async fn guest_can_rejoin_after_disconnecting() {
+    let (mut host, mut guests) = session(1).await;
+    let guest = guests.pop().unwrap();
+    let guest_id = guest.peer_id.clone();
+
+    let mut guest = guest.restart(&[]).await;
+    assert_eq!(guest.peer_id, guest_id, "identity should survive a restart");
+    let gone = host.expect("peer-disconnected").await;
+    assert_eq!(gone["peer_id"], guest_id.as_str());
+    assert!(host.state.session_peers.lock().unwrap().is_empty());
+
+    let join = guest.join(&host, host.addrs());
+    host.expect_where("join-requested", |p| p["peer_id"] == guest_id.as_str()).await;
+    host.approve(&guest_id, &[]).await.unwrap();
+    join.await.unwrap().unwrap();
+    guest.expect("join-accepted").await;
+    assert!(host.state.session_peers.lock().unwrap().contains_key(&guest_id));
+}
+
+#[tokio::test]
+async fn guests_notice_when_the_host_leaves() {
+    let (host, mut guests) = session(1).await;
+    let host_id = host.peer_id.clone();
+    let _ = host.shutdown();
+
+    let gone = guests[0].expect("host-disconnected").await;
+    assert_eq!(gone, host_id.as_str());
+    assert!(guests[0].state.session_peers.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn guest_joins_through_the_relay() {
+    let relay = spawn_relay().await;
+    let relays = [relay.clone()];
+
+    let mut host = TestPeer::spawn(&relays).await;
+    host.expect_where("new-listen-addr", |a| a.as_str().unwrap().contains("p2p-circuit")).await;
+
+    // No direct address: the guest can only reach the host over its reservation
+    let mut guest = TestPeer::spawn(&relays).await;
+    guest.expect_where("new-listen-addr", |a| a.as_str().unwrap().contains("p2p-circuit")).await;
+    let join = guest.join(&host, Vec::new());
+    host.expect_where("join-requested", |p| p["peer_id"] == guest.peer_id.as_str()).await;
+    host.approve(&guest.peer_id, &[]).await.unwrap();
+    join.await.unwrap().unwrap();
+    guest.expect("join-accepted").await;
+
+    let (path, data) = ("d.md".to_string(), text_update("relayed"));
+    guest.command(|reply| SwarmCommand::Sync { path, data, reply }).await.unwrap();
+    host.expect_where("p2p-sync", |p| p["path"] == "d.md").await;
+    assert_eq!(host.document_text("d.md").as_deref(), Some("relayed"));
+}
+
+#[tokio::test]
+async fn payloads_without_the_session_key_are_dropped() {
+    let (mut host, guests) = session(1).await;
+    let epoch = |peer: &TestPeer| peer.state.session_keys.lock().unwrap().current().map(|c| c.epoch());
+    assert_eq!(epoch(&host), Some(1));
+    assert_eq!(epoch(&guests[0]), Some(1));
+
+    // A plaintext update and one sealed with a made-up key both get nowhere
+    let forged = [(0, text_update("plain")), (1, vec![0; 64])];
+    for (i, (epoch, data)) in forged.into_iter().enumerate() {
+        let request = AppRequest::Sync { path: "a.md".into(), data, id: format!("forged-{}", i), epoch };
+        assert_eq!(legacy_request(&host, request).await, Ok(AppResponse::Ack));
+    }
+    host.expect_none("p2p-sync", Duration::from_millis(300)).await;
+    assert_eq!(host.document_text("a.md"), None);
+}
+
+#[tokio::test]
+async fn session_key_rotates_when_a_guest_leaves() {
+    let (mut host, mut guests) = session(2).await;
+    let _ = guests.pop().unwrap().shutdown();
+
+    assert_eq!(host.expect("session-key-rotated").await, 2);
+    assert_eq!(guests[0].expect("session-key-rotated").await, 2);
+
+    let (path, data) = ("e.md".to_string(), text_update("after rotation"));
+    host.command(|reply| SwarmCommand::Sync { path, data, reply }).await.unwrap();
+    guests[0].expect_where("p2p-sync", |p| p["path"] == "e.md").await;
+    assert_eq!(guests[0].document_text("e.md").as_deref(), Some("after rotation"));
+}
+
+#[tokio::test]
+async fn edits_during_a_rotation_reach_guests_once_they_hold_the_key() {
+    let (mut host, mut guests) = session(2).await;
+
+    // The host moves on to a key whose grant the guest has not received yet,
+    // while the guest keeps editing with the old one
+    host.state.session_keys.lock().unwrap().rotate();
+    let (path, data) = ("r.md".to_string(), text_update("host "));
+    host.command(|reply| SwarmCommand::Sync { path, data, reply }).await.unwrap();
+    let (path, data) = ("g.md".to_string(), text_update("guest"));
+    guests[0].command(|reply| SwarmCommand::Sync { path, data, reply }).await.unwrap();
+    host.expect_where("p2p-sync", |p| p["path"] == "g.md").await;
+    guests[0].expect_none("p2p-sync", Duration::from_millis(300)).await;
+    assert_eq!(guests[0].document_text("r.md"), None);
+
+    // The next grant lets the guest catch up on what it could not open
+    let _ = guests.pop().unwrap().shutdown();
+    assert_eq!(guests[0].expect("session-key-rotated").await, 3);
+    guests[0].expect_where("p2p-sync", |p| p["path"] == "r.md").await;
+    assert_eq!(guests[0].document_text("r.md").as_deref(), Some("host "));
+    assert_eq!(host.document_text("g.md").as_deref(), Some("guest"));
+}
+
+#[tokio::test]
+async fn viewers_follow_along_but_cannot_edit() {
+    let mut host = TestPeer::spawn(&[]).await;
+    let mut viewer = TestPeer::spawn(&[]).await;
+    let join = viewer.join(&host, host.addrs());
+    host.expect("join-requested").await;
+    host.approve_as(&viewer.peer_id, SessionRole::Viewer, &[]).await.unwrap();
+    join.await.unwrap().unwrap();
+    viewer.expect("join-accepted").await;
+    assert_eq!(*viewer.state.local_role.lock().unwrap(), Some(SessionRole::Viewer));
+    assert_eq!(host.state.session_peers.lock().unwrap()[&viewer.peer_id].session_role, SessionRole::Viewer);
+
+    let (path, data) = ("a.md".to_string(), text_update("from viewer"));
+    assert!(viewer.command(|reply| SwarmCommand::Sync { path, data, reply }).await.is_err());
+    host.expect_none("p2p-sync", Duration::from_millis(300)).await;
+
+    let (path, data) = ("a.md".to_string(), text_update("from host"));
+    host.command(|reply| SwarmCommand::Sync { path, data, reply }).await.unwrap();
+    viewer.expect_where("p2p-sync", |p| p["path"] == "a.md").await;
+    assert_eq!(viewer.document_text("a.md").as_deref(), Some("from host"));
+}
+
+#[tokio::test]
+async fn host_can_demote_an_editor_to_viewer() {
+    let (mut host, mut guests) = session(1).await;
+    let guest = &mut guests[0];
+
+    let peer_id = guest.peer_id.clone();
+    host.command(|reply| SwarmCommand::SetPeerRole { peer_id, role: SessionRole::Viewer, reply }).await.unwrap();
+    let changed = host.expect("peer-role-changed").await;
+    assert_eq!(changed["session_role"], "viewer");
+    assert_eq!(guest.expect("session-role-changed").await, "viewer");
+
+    let (path, data) = ("a.md".to_string(), text_update("too late"));
+    assert!(guest.command(|reply| SwarmCommand::Sync { path, data, reply }).await.is_err());
+
+    let peer_id = guest.peer_id.clone();
+    assert!(host.command(|reply| SwarmCommand::SetPeerRole { peer_id, role: SessionRole::Host, reply }).await.is_err());
+}
+
+#[tokio::test]
+async fn edits_outside_the_project_are_dropped() {
+    let (mut host, guests) = session(1).await;
+
+    let (path, data) = ("../escape.md".to_string(), b"outside".to_vec());
+    guests[0].command(|reply| SwarmCommand::FileContent { path, data, reply }).await.unwrap();
+    let denied = host.expect("permission-denied").await;
+    assert_eq!(denied["peer_id"], guests[0].peer_id.as_str());
+    assert_eq!(denied["action"], "file-content");
+    assert_eq!(denied["path"], "../escape.md");
+    host.expect_none("p2p-file-content", Duration::from_millis(300)).await;
+}
+
+#[tokio::test]
+async fn trusted_peers_join_without_asking() {
+    let mut host = TestPeer::spawn(&[]).await;
+    let mut guest = TestPeer::spawn(&[]).await;
+    host.state.workspace.lock().unwrap().open(host.project_dir()).unwrap();
+    host.state.trust.lock().unwrap().set(&guest.peer_id, TrustLevel::Trusted, "Ada").unwrap();
+
+    guest.join(&host, host.addrs()).await.unwrap().unwrap();
+    guest.expect("join-accepted").await;
+    let approved = host.expect("join-auto-approved").await;
+    assert_eq!(approved["peer_id"], guest.peer_id.as_str());
+    assert!(host.state.session_peers.lock().unwrap().contains_key(&guest.peer_id));
+    assert!(host.state.pending_invites.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn blocked_peers_never_reach_the_host() {
+    let mut host = TestPeer::spawn(&[]).await;
+    let guest = TestPeer::spawn(&[]).await;
+    host.state.trust.lock().unwrap().set(&guest.peer_id, TrustLevel::Blocked, "").unwrap();
+
+    assert!(guest.join(&host, host.addrs()).await.unwrap().is_err());
+    assert_eq!(host.expect("join-blocked").await, guest.peer_id.as_str());
+    host.expect_none("join-requested", Duration::from_millis(300)).await;
+    assert!(host.state.pending_invites.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn kicked_guests_leave_and_may_rejoin() {
+    let (mut host, mut guests) = session(1).await;
+    let mut guest = guests.remove(0);
+    let peer_id = guest.peer_id.clone();
+
+    host.command(|reply| SwarmCommand::Kick { peer_id: peer_id.clone(), reason: Some("Wrap-up".into()), ban: false, reply }).await.unwrap();
+    let ended = guest.expect("session-ended").await;
+    assert_eq!(ended["peer_id"], host.peer_id.as_str());
+    assert_eq!(ended["reason"], "Wrap-up");
+    assert_eq!(host.expect("peer-kicked").await["banned"], false);
+    assert!(!host.state.session_peers.lock().unwrap().contains_key(&peer_id));
+    assert!(!host.state.active_peers.lock().unwrap().contains(&peer_id));
+    assert!(guest.state.local_role.lock().unwrap().is_none());
+    guest.expect_none("host-disconnected", Duration::from_millis(300)).await;
+
+    // Let the host close the old connection before dialing again
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let join = guest.join(&host, host.addrs());
+    host.expect_where("join-requested", |p| p["peer_id"] == peer_id.as_str()).await;
+    host.approve(&peer_id, &[]).await.unwrap();
+    join.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn banned_guests_cannot_rejoin() {
+    let (mut host, mut guests) = session(1).await;
+    let mut guest = guests.remove(0);
+    let peer_id = guest.peer_id.clone();
+
+    host.command(|reply| SwarmCommand::Kick { peer_id: peer_id.clone(), reason: None, ban: true, reply }).await.unwrap();
+    assert_eq!(guest.expect("session-ended").await["reason"], "You were banned from the session");
+    assert_eq!(host.expect("peer-kicked").await["banned"], true);
+    let saved = TrustList::load(host.data_dir());
+    assert!(saved.is_blocked(&peer_id.parse().unwrap()));
+    assert_eq!(saved.entries()[0].note, "Guest");
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(guest.join(&host, host.addrs()).await.unwrap().is_err());
+    assert_eq!(host.expect("join-blocked").await, peer_id.as_str());
+}
+
+#[tokio::test]
+async fn strangers_cannot_chat_or_show_cursors_in_a_session() {
+    let (mut host, mut guests) = session(1).await;
+    let mut guest = guests.remove(0);
+
+    let cursor = AppRequest::Awareness { entries: vec![AwarenessEntry { client_id: 7, clock: 0, state: Some("{}".into()) }] };
+    assert_eq!(legacy_request(&host, cursor).await, Ok(AppResponse::Ack));
+    let chat = AppRequest::Chat { author: guest.peer_id.clone(), text: "spoofed".into(), id: String::new() };
+    assert_eq!(legacy_request(&host, chat).await, Ok(AppResponse::Ack));
+    host.expect_none("chat-message", Duration::from_millis(300)).await;
+    host.expect_none("awareness-changed", Duration::from_millis(100)).await;
+    guest.expect_none("chat-message", Duration::from_millis(100)).await;
+    assert!(host.state.awareness.lock().unwrap().states().is_empty());
+
+    guest.command(|reply| SwarmCommand::Chat { text: "hello".into(), reply }).await.unwrap();
+    let said = host.expect("chat-message").await;
+    assert_eq!((said["peer_id"].as_str(), said["text"].as_str()), (Some(guest.peer_id.as_str()), Some("hello")));
+}
+
+#[tokio::test]
+async fn chat_relayed_by_the_host_is_not_marked_verified() {
+    let (mut host, mut guests) = session(2).await;
+
+    guests[0].command(|reply| SwarmCommand::Chat { text: "hi all".into(), reply }).await.unwrap();
+    let direct = host.expect("chat-message").await;
+    assert_eq!((direct["peer_id"].as_str(), direct["verified"].as_bool()), (Some(guests[0].peer_id.as_str()), Some(true)));
+    let relayed = guests[1].expect("chat-message").await;
+    assert_eq!((relayed["peer_id"].as_str(), relayed["verified"].as_bool()), (Some(guests[0].peer_id.as_str()), Some(false)));
+
+    host.command(|reply| SwarmCommand::Chat { text: "welcome".into(), reply }).await.unwrap();
+    let from_host = guests[1].expect("chat-message").await;
+    assert_eq!((from_host["peer_id"].as_str(), from_host["verified"].as_bool()), (Some(host.peer_id.as_str()), Some(true)));
+}
+
+#[tokio::test]
+async fn gossip_sessions_deliver_host_updates_and_ignore_outsiders() {
+    let gossip = |config: &mut NetworkConfig| config.gossipsub = true;
+    let mut host = TestPeer::spawn_with(&[], gossip).await;
+    let mut guest = TestPeer::spawn_with(&[], gossip).await;
+    let join = guest.join(&host, host.addrs());
+    host.expect("join-requested").await;
+    host.approve(&guest.peer_id, &[]).await.unwrap();
+    join.await.unwrap().unwrap();
+    guest.expect("join-accepted").await;
+
+    // The topic name is guessable, but subscribing to it is not joining
+    let forged = vec![
+        GossipMessage::Chat { author: host.peer_id.clone(), text: "spoofed".into(), id: "outsider:1".into() },
+        GossipMessage::Awareness { entries: vec![AwarenessEntry { client_id: 7, clock: 0, state: Some("{}".into()) }] },
+    ];
+    gossip_as_outsider(&host, "project", forged).await;
+    host.expect_none("chat-message", Duration::from_millis(300)).await;
+    guest.expect_none("chat-message", Duration::from_millis(100)).await;
+    guest.expect_none("awareness-changed", Duration::from_millis(100)).await;
+    assert!(host.state.awareness.lock().unwrap().states().is_empty());
+
+    let data = text_update("over gossip");
+    let (path, update) = ("a.md".to_string(), data.clone());
+    host.command(|reply| SwarmCommand::Sync { path, data: update, reply }).await.unwrap();
+    let sync = guest.expect("p2p-sync").await;
+    assert_eq!(bytes(&sync["data"]), data);
+    assert_eq!(guest.document_text("a.md").as_deref(), Some("over gossip"));
+
+    guest.command(|reply| SwarmCommand::Chat { text: "hello".into(), reply }).await.unwrap();
+    assert_eq!(host.expect("chat-message").await["peer_id"], guest.peer_id.as_str());
+}
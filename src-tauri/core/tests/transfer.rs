@@ -0,0 +1,213 @@
+mod common;
+
+use common::TestPeer;
+use my_editor_core::files::{self, SkipReason, SkippedEntry};
+use my_editor_core::merkle::ProjectTree;
+use my_editor_core::protocol::SwarmCommand;
+use my_editor_core::transfer;
+
+#[tokio::test]
+async fn project_streams_in_chunks_with_progress() {
+    let mut host = TestPeer::spawn(&[]).await;
+    let mut guest = TestPeer::spawn(&[]).await;
+
+    // Spans several chunks and progress intervals
+    let big: Vec<u8> = (0..3 * 1024 * 1024 + 17).map(|i| (i % 251) as u8).collect();
+    let join = guest.join(&host, host.addrs());
+    host.expect("join-requested").await;
+    host.approve(&guest.peer_id, &[("assets/big.bin", &big), ("src/main.md", b"# title"), (".git/HEAD", b"ignored")]).await.unwrap();
+    join.await.unwrap().unwrap();
+
+    let progress = guest.expect_where("transfer-progress", |p| p["bytes_done"].as_u64() > Some(0)).await;
+    assert_eq!(progress["direction"], "receive");
+    assert_eq!(progress["files_total"], 2);
+    assert_eq!(progress["bytes_total"], big.len() as u64 + 7);
+
+    let done = guest.expect("transfer-complete").await;
+    assert_eq!(done["files_done"], 2);
+    assert_eq!(done["bytes_done"], big.len() as u64 + 7);
+    let sent = host.expect("transfer-complete").await;
+    assert_eq!(sent["direction"], "send");
+    assert_eq!(sent["peer_id"], guest.peer_id.as_str());
+
+    assert_eq!(std::fs::read(guest.staged_file(&host, "assets/big.bin")).unwrap(), big);
+    assert!(!guest.staged_file(&host, ".git").exists());
+    let staged = guest.staged_from(&host);
+
+    let dest = tempfile::TempDir::new().unwrap();
+    files::save_incoming_project(dest.path(), &staged).unwrap();
+    assert_eq!(std::fs::read(dest.path().join("src/main.md")).unwrap(), b"# title");
+    assert!(!staged.exists());
+}
+
+#[tokio::test]
+async fn cancelling_a_transfer_fails_it_on_both_sides() {
+    let mut host = TestPeer::spawn(&[]).await;
+    let mut guest = TestPeer::spawn(&[]).await;
+
+    let big = vec![7u8; 64 * 1024 * 1024];
+    let join = guest.join(&host, host.addrs());
+    host.expect("join-requested").await;
+    host.approve(&guest.peer_id, &[("huge.bin", &big)]).await.unwrap();
+    join.await.unwrap().unwrap();
+
+    guest.expect_where("transfer-progress", |p| p["bytes_done"].as_u64() > Some(0)).await;
+    let peer_id = host.peer_id.clone();
+    guest.command(|reply| SwarmCommand::CancelTransfer { peer_id, reply }).await.unwrap();
+    guest.expect("transfer-cancelled").await;
+
+    let failed = host.expect("transfer-failed").await;
+    assert_eq!(failed["direction"], "send");
+    assert!(failed["error"].is_string());
+
+    let peer_id = host.peer_id.clone();
+    assert!(guest.command(|reply| SwarmCommand::CancelTransfer { peer_id, reply }).await.is_err());
+}
+
+#[tokio::test]
+async fn rejoining_guest_only_receives_changes() {
+    let mut host = TestPeer::spawn(&[]).await;
+    let mut guest = TestPeer::spawn(&[]).await;
+
+    // Yesterday's copy: one file unchanged, one stale, one deleted on the host since
+    let copy = tempfile::TempDir::new().unwrap();
+    for (path, content) in [("same.md", "same"), ("docs/changed.md", "old"), ("old/gone.md", "gone")] {
+        let path = copy.path().join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    let tree = ProjectTree::build(copy.path()).unwrap();
+
+    let join = guest.join_with(&host, host.addrs(), Some(tree));
+    host.expect("join-requested").await;
+    host.approve(&guest.peer_id, &[("same.md", b"same"), ("docs/changed.md", b"new"), ("added.md", b"added")]).await.unwrap();
+    join.await.unwrap().unwrap();
+
+    let done = guest.expect("transfer-complete").await;
+    assert_eq!(done["files_total"], 2);
+    assert_eq!(done["files_deleted"], 1);
+
+    files::save_incoming_project(copy.path(), &guest.staged_from(&host)).unwrap();
+    assert_eq!(std::fs::read_to_string(copy.path().join("docs/changed.md")).unwrap(), "new");
+    assert_eq!(std::fs::read_to_string(copy.path().join("added.md")).unwrap(), "added");
+    assert_eq!(std::fs::read_to_string(copy.path().join("same.md")).unwrap(), "same");
+    assert!(!copy.path().join("old").exists());
+    assert_eq!(ProjectTree::build(copy.path()).unwrap(), ProjectTree::build(host.project_dir()).unwrap());
+}
+
+#[test]
+fn tree_diff_skips_matching_subtrees_and_handles_type_changes() {
+    let base = tempfile::TempDir::new().unwrap();
+    let ours = tempfile::TempDir::new().unwrap();
+    for (root, files) in [
+        (base.path(), vec![("lib/a.rs", "a"), ("lib/b.rs", "b"), ("swap", "file"), ("dir/x", "x")]),
+        (ours.path(), vec![("lib/a.rs", "a"), ("lib/b.rs", "b"), ("swap/inner", "now a dir"), ("dir", "now a file")]),
+    ] {
+        for (path, content) in files {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+    }
+
+    let diff = ProjectTree::build(ours.path()).unwrap().diff(&ProjectTree::build(base.path()).unwrap());
+    assert_eq!(diff.changed, vec!["dir".to_string(), "swap/inner".to_string()]);
+    assert_eq!(diff.deleted, vec!["dir/x".to_string(), "swap".to_string()]);
+}
+
+/// A staging folder as a transfer would leave it, with `files` received and
+/// `deleted` announced.
+fn staging(files: &[(&str, &[u8])], deleted: &[&str]) -> tempfile::TempDir {
+    let staging = tempfile::TempDir::new().unwrap();
+    for (path, content) in files {
+        let path = staging.path().join(transfer::STAGED_FILES).join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    std::fs::create_dir_all(staging.path().join(transfer::STAGED_FILES)).unwrap();
+    std::fs::write(staging.path().join(transfer::STAGED_DELETIONS), serde_json::to_vec(deleted).unwrap()).unwrap();
+    staging
+}
+
+#[cfg(unix)]
+#[test]
+fn saving_skips_entries_that_could_escape_the_destination() {
+    let outside = tempfile::TempDir::new().unwrap();
+    std::fs::write(outside.path().join("victim"), "keep").unwrap();
+    let dest = tempfile::TempDir::new().unwrap();
+    std::os::unix::fs::symlink(outside.path(), dest.path().join("linked")).unwrap();
+
+    let staged = staging(&[("ok.md", b"ok"), ("linked/victim", b"overwritten")], &["../victim", "linked/victim", "/etc/hosts"]);
+    std::os::unix::fs::symlink(outside.path().join("victim"), staged.path().join(transfer::STAGED_FILES).join("link.md")).unwrap();
+
+    let mut report = files::save_incoming_project(dest.path(), staged.path()).unwrap();
+    report.skipped.sort_by(|a, b| a.path.cmp(&b.path));
+    let skipped = |path: &str, reason| SkippedEntry { path: path.to_string(), reason };
+    assert_eq!(report.skipped, vec![
+        skipped("../victim", SkipReason::UnsafePath),
+        skipped("/etc/hosts", SkipReason::UnsafePath),
+        skipped("link.md", SkipReason::Symlink),
+        skipped("linked/victim", SkipReason::Symlink),
+        skipped("linked/victim", SkipReason::Symlink),
+    ]);
+    assert_eq!((report.written, report.deleted), (1, 0));
+    assert_eq!(std::fs::read_to_string(dest.path().join("ok.md")).unwrap(), "ok");
+    assert_eq!(std::fs::read_to_string(outside.path().join("victim")).unwrap(), "keep");
+}
+
+#[tokio::test]
+async fn unsafe_entries_from_the_host_are_skipped_not_fatal() {
+    let mut host = TestPeer::spawn(&[]).await;
+    let mut guest = TestPeer::spawn(&[]).await;
+
+    // A baseline naming files outside the project makes the host announce
+    // them as deleted
+    let file = serde_json::json!({ "File": { "hash": "0" } });
+    let outside = serde_json::json!({ "Dir": { "hash": "1", "children": { "escape.md": file } } });
+    let tree: ProjectTree = serde_json::from_value(serde_json::json!({ "root": { "Dir": { "hash": "2", "children": { "..": outside } } } })).unwrap();
+
+    let join = guest.join_with(&host, host.addrs(), Some(tree));
+    host.expect("join-requested").await;
+    host.approve(&guest.peer_id, &[("ok.md", b"ok")]).await.unwrap();
+    join.await.unwrap().unwrap();
+
+    let done = guest.expect("transfer-complete").await;
+    assert_eq!((done["files_done"].as_u64(), done["files_deleted"].as_u64()), (Some(1), Some(1)));
+
+    let dest = tempfile::TempDir::new().unwrap();
+    let report = files::save_incoming_project(dest.path(), &guest.staged_from(&host)).unwrap();
+    assert_eq!(report.skipped, vec![SkippedEntry { path: "../escape.md".to_string(), reason: SkipReason::UnsafePath }]);
+    assert_eq!((report.written, report.deleted), (1, 0));
+    assert_eq!(std::fs::read_to_string(dest.path().join("ok.md")).unwrap(), "ok");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn names_reserved_on_windows_transfer_elsewhere() {
+    let mut host = TestPeer::spawn(&[]).await;
+    let mut guest = TestPeer::spawn(&[]).await;
+
+    let join = guest.join(&host, host.addrs());
+    host.expect("join-requested").await;
+    host.approve(&guest.peer_id, &[("aux.c", b"int x;"), ("src/con.h", b"#pragma once"), ("a:b.txt", b"colon")]).await.unwrap();
+    join.await.unwrap().unwrap();
+    guest.expect("transfer-complete").await;
+
+    let dest = tempfile::TempDir::new().unwrap();
+    let report = files::save_incoming_project(dest.path(), &guest.staged_from(&host)).unwrap();
+    assert_eq!((report.written, report.skipped.len()), (3, 0));
+    assert_eq!(std::fs::read_to_string(dest.path().join("src/con.h")).unwrap(), "#pragma once");
+    assert_eq!(std::fs::read_to_string(dest.path().join("a:b.txt")).unwrap(), "colon");
+}
+
+#[test]
+fn oversized_projects_are_refused_before_touching_the_destination() {
+    let staged = staging(&[("small.md", b"small")], &[]);
+    let big = std::fs::File::create(staged.path().join(transfer::STAGED_FILES).join("big.bin")).unwrap();
+    big.set_len(files::MAX_IMPORT_BYTES).unwrap();
+
+    let dest = tempfile::TempDir::new().unwrap();
+    assert!(files::save_incoming_project(dest.path(), staged.path()).is_err());
+    assert!(!dest.path().join("small.md").exists());
+    assert!(staged.path().exists());
+}
@@ -0,0 +1,27 @@
+use libp2p::PeerId;
+use my_editor_core::trust::{TrustLevel, TrustList};
+use tempfile::TempDir;
+
+#[test]
+fn trust_list_survives_a_restart() {
+    let data_dir = TempDir::new().unwrap();
+    let (teammate, stranger) = (PeerId::random(), PeerId::random());
+    let mut list = TrustList::default();
+    list.set(&teammate.to_string(), TrustLevel::Trusted, "  Ada's laptop ").unwrap();
+    list.set(&stranger.to_string(), TrustLevel::Blocked, "").unwrap();
+    assert!(list.set("not-a-peer", TrustLevel::Trusted, "").is_err());
+    assert!(list.set(&teammate.to_string(), TrustLevel::Trusted, &"x".repeat(201)).is_err());
+    list.save(data_dir.path()).unwrap();
+
+    let loaded = TrustList::load(data_dir.path());
+    assert_eq!(loaded, list);
+    assert!(loaded.is_trusted(&teammate));
+    assert!(loaded.is_blocked(&stranger));
+    assert_eq!(loaded.entries().iter().find(|e| e.peer_id == teammate.to_string()).unwrap().note, "Ada's laptop");
+
+    let mut list = loaded;
+    assert!(list.remove(&stranger.to_string()));
+    assert!(!list.remove(&stranger.to_string()));
+    assert_eq!(list.level(&stranger), None);
+    assert_eq!(TrustList::load(TempDir::new().unwrap().path()), TrustList::default());
+}
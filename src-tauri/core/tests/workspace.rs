@@ -0,0 +1,59 @@
+use my_editor_core::workspace::{Workspace, WorkspaceError};
+use tempfile::TempDir;
+
+fn outside(path: &str) -> Result<std::path::PathBuf, WorkspaceError> {
+    Err(WorkspaceError::PathOutsideWorkspace { path: path.to_string() })
+}
+
+#[test]
+fn paths_resolve_inside_the_open_project_only() {
+    let project = TempDir::new().unwrap();
+    let other = TempDir::new().unwrap();
+    std::fs::create_dir(project.path().join("src")).unwrap();
+    let mut workspace = Workspace::default();
+    assert_eq!(workspace.resolve("a.md"), Err(WorkspaceError::NoWorkspace));
+
+    workspace.open(project.path()).unwrap();
+    assert_eq!(workspace.resolve("src/a.md").unwrap(), project.path().join("src/a.md"));
+    let absolute = project.path().join("new/dir/b.md").to_string_lossy().to_string();
+    assert_eq!(workspace.resolve(&absolute).unwrap(), project.path().join("new/dir/b.md"));
+
+    assert_eq!(workspace.resolve("../escape.md"), outside("../escape.md"));
+    let sneaky = format!("{}/src/../../escape.md", project.path().display());
+    assert_eq!(workspace.resolve(&sneaky), outside(&sneaky));
+    let elsewhere = other.path().join("a.md").to_string_lossy().to_string();
+    assert_eq!(workspace.resolve(&elsewhere), outside(&elsewhere));
+
+    workspace.close();
+    let inside = project.path().join("a.md").to_string_lossy().to_string();
+    assert_eq!(workspace.resolve(&inside), outside(&inside));
+}
+
+#[test]
+fn allowed_directories_are_reachable_without_a_project() {
+    let data = TempDir::new().unwrap();
+    let mut workspace = Workspace::default();
+    // Not created yet, as on first launch
+    workspace.allow(data.path().join("plugins"));
+
+    let manifest = data.path().join("plugins/demo/plugin.json").to_string_lossy().to_string();
+    assert!(workspace.resolve(&manifest).is_ok());
+    let sibling = data.path().join("network_config.json").to_string_lossy().to_string();
+    assert_eq!(workspace.resolve(&sibling), outside(&sibling));
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_cannot_lead_out_of_the_project() {
+    let project = TempDir::new().unwrap();
+    let secrets = TempDir::new().unwrap();
+    std::fs::write(secrets.path().join("id_ed25519"), b"key").unwrap();
+    std::os::unix::fs::symlink(secrets.path(), project.path().join("linked")).unwrap();
+    std::os::unix::fs::symlink(secrets.path().join("missing"), project.path().join("dangling")).unwrap();
+    let mut workspace = Workspace::default();
+    workspace.open(project.path()).unwrap();
+
+    assert_eq!(workspace.resolve("linked/id_ed25519"), outside("linked/id_ed25519"));
+    assert_eq!(workspace.resolve("linked/new.md"), outside("linked/new.md"));
+    assert_eq!(workspace.resolve("dangling"), outside("dangling"));
+}
@@ -0,0 +1,91 @@
+use libp2p::{
+    connection_limits,
+    futures::StreamExt,
+    identify, identity, noise, ping, relay,
+    swarm::{NetworkBehaviour, SwarmEvent},
+    tcp, yamux, PeerId, Swarm, SwarmBuilder,
+};
+use std::error::Error;
+use std::time::Duration;
+
+// 1. Add limits to your Behaviour Struct
+#[derive(NetworkBehaviour)]
+pub struct RelayNodeBehaviour {
+    relay: relay::Behaviour,
+    identify: identify::Behaviour,
+    ping: ping::Behaviour,
+    limits: connection_limits::Behaviour,
+}
+
+/// Builds the relay swarm for `id_keys`. The caller picks the listen and
+/// external addresses before handing it to [`run`].
+pub fn build_swarm(id_keys: identity::Keypair) -> Result<Swarm<RelayNodeBehaviour>, Box<dyn Error>> {
+    let local_peer_id = PeerId::from(id_keys.public());
+
+    // 2. Define the Limits
+    let limits = connection_limits::ConnectionLimits::default()
+        .with_max_pending_incoming(Some(10))
+        .with_max_established_incoming(Some(50))
+        .with_max_established_per_peer(Some(2));
+
+    let behaviour = RelayNodeBehaviour {
+        relay: relay::Behaviour::new(
+            local_peer_id,
+            relay::Config {
+                max_reservations: 32,
+                max_circuits: 4,
+                reservation_duration: Duration::from_secs(30 * 60),
+                max_circuit_duration: Duration::from_secs(2 * 60),
+                max_circuit_bytes: 10 * 1024 * 1024,
+                ..Default::default()
+            },
+        ),
+        identify: identify::Behaviour::new(identify::Config::new(
+            "/relay/1.0.0".to_string(),
+            id_keys.public(),
+        )),
+        ping: ping::Behaviour::new(ping::Config::new()),
+        // 3. Initialize the Behaviour with the limits
+        limits: connection_limits::Behaviour::new(limits),
+    };
+
+    let swarm = SwarmBuilder::with_existing_identity(id_keys)
+        .with_tokio()
+        .with_tcp(
+            tcp::Config::default(),
+            noise::Config::new,
+            yamux::Config::default,
+        )?
+        .with_quic()
+        .with_behaviour(|_| behaviour)?
+        .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+        .build();
+
+    Ok(swarm)
+}
+
+/// Drives the relay forever, logging reservations and circuits.
+pub async fn run(mut swarm: Swarm<RelayNodeBehaviour>) {
+    loop {
+        match swarm.select_next_some().await {
+            SwarmEvent::NewListenAddr { address, .. } => {
+                println!("Listening locally on: {}", address);
+            }
+            SwarmEvent::Behaviour(RelayNodeBehaviourEvent::Relay(
+                relay::Event::ReservationReqAccepted { src_peer_id, .. },
+            )) => {
+                println!("Relay accepted reservation from: {}", src_peer_id);
+            }
+            SwarmEvent::Behaviour(RelayNodeBehaviourEvent::Relay(
+                relay::Event::CircuitReqAccepted {
+                    src_peer_id,
+                    dst_peer_id,
+                    ..
+                },
+            )) => {
+                println!("Relay accepted circuit: {} -> {}", src_peer_id, dst_peer_id);
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,230 +1,102 @@
-use tauri::{command, State}; use std::sync::Arc; use tokio::sync::Mutex; use crate::protocol::{Payload, FileSyncEntry}; use crate::state::PeerState; use std::fs; use std::path::Path; use serde::Serialize; use git2::{Repository}; use std::process::Command;
-
-type SenderState<'a> = State<'a, Arc<Mutex<tokio::sync::mpsc::Sender<(String, Payload)>>>>;
-
-#[derive(Serialize, Clone)] pub struct FileEntry { name: String, path: String, is_dir: bool, }
-
-fn visit_dirs(dir: &Path, base: &Path, cb: &mut Vec<FileSyncEntry>) -> std::io::Result<()> { if dir.is_dir() { for entry in fs::read_dir(dir)? { let entry = entry?; let path = entry.path(); let file_name = path.file_name().unwrap_or_default().to_string_lossy();
-
-        if file_name == ".git" || file_name == "node_modules" || file_name == "target" {
-            continue;
-        }
-
-        if path.is_dir() {
-            visit_dirs(&path, base, cb)?;
-        } else {
-            if let Ok(relative) = path.strip_prefix(base) {
-                let relative_str = relative.to_string_lossy().replace("\\", "/");
-                let content = fs::read(&path)?;
-                cb.push(FileSyncEntry {
-                    path: relative_str,
-                    content,
-                });
-            }
-        }
-    }
-}
-Ok(())
-
-}
+use tauri::{command, State}; use std::sync::Arc; use tokio::sync::Mutex; use my_editor_core::protocol::{SwarmCommand, Reply, AwarenessEntry, JoinProfile}; use my_editor_core::state::{DiscoveredPeer, PeerState}; use my_editor_core::config::NetworkConfig; use my_editor_core::peers::{SessionPeer, SessionRole}; use my_editor_core::files::{self, FileEntry, ImportReport}; use my_editor_core::git; use my_editor_core::transfer; use my_editor_core::merkle::ProjectTree; use my_editor_core::workspace::WorkspaceError; use my_editor_core::trust::{TrustLevel, TrustedPeer}; use tauri::{AppHandle, Manager}; use tauri_plugin_dialog::DialogExt; use std::path::PathBuf;
 
-#[command] pub fn get_local_peer_id(state: State<'_, PeerState>) -> Result<String, String> { state.local_peer_id.lock().unwrap_or_else(|e| e.into_inner()).clone().ok_or("Peer ID not initialized".into()) }
+type SenderState<'a> = State<'a, Arc<Mutex<tokio::sync::mpsc::Sender<SwarmCommand>>>>;
 
-#[command] pub fn git_pull(path: String, ssh_key_path: String) -> Result<String, String> { let mut cmd = Command::new("git"); cmd.current_dir(&path); cmd.arg("pull");
-
-if !ssh_key_path.trim().is_empty() {
-    #[cfg(target_os = "windows")]
-    let ssh_cmd = format!("ssh -i \"{}\"", ssh_key_path.replace("\\", "\\\\"));
-    #[cfg(not(target_os = "windows"))]
-    let ssh_cmd = format!("ssh -i \"{}\"", ssh_key_path);
-    cmd.env("GIT_SSH_COMMAND", ssh_cmd);
-}
-cmd.env("GIT_TERMINAL_PROMPT", "0");
-
-// [FIX] Aggressively sanitize environment on Linux to prevent X11/Wayland connection theft
-#[cfg(target_os = "linux")]
-{
-    cmd.env_remove("DISPLAY");
-    cmd.env_remove("GDK_BACKEND");
-    cmd.env_remove("GTK_MODULES");
-    cmd.env_remove("SSH_ASKPASS");
-    cmd.env_remove("GIT_ASKPASS");
-    cmd.env_remove("SSH_AUTH_SOCK"); // Prevent interference from system agent
-    cmd.env_remove("XAUTHORITY");
-}
+/// Resolves a path from the webview inside the open project or an allowed directory.
+fn sandboxed(state: &State<'_, PeerState>, path: &str) -> Result<PathBuf, WorkspaceError> { state.workspace.lock().unwrap_or_else(|e| e.into_inner()).resolve(path) }
 
-#[cfg(not(target_os = "linux"))]
-{
-    cmd.env("SSH_ASKPASS", "false");
-    cmd.env("GIT_ASKPASS", "false");
+/// Sends a command to the swarm task and waits for its outcome.
+async fn dispatch<T>(sender: &SenderState<'_>, command: impl FnOnce(Reply<T>) -> SwarmCommand) -> Result<T, String> {
+    let (reply, outcome) = tokio::sync::oneshot::channel();
+    sender.lock().await.send(command(reply)).await.map_err(|e| e.to_string())?;
+    outcome.await.map_err(|_| "Network task stopped before replying".to_string())?
 }
 
-let output = cmd.output().map_err(|e| format!("Git pull failed: {}", e))?;
+#[command] pub fn get_local_peer_id(state: State<'_, PeerState>) -> Result<String, String> { state.local_peer_id.lock().unwrap_or_else(|e| e.into_inner()).clone().ok_or("Peer ID not initialized".into()) }
 
-if output.status.success() {
-    Ok(String::from_utf8_lossy(&output.stdout).to_string())
-} else {
-    Err(String::from_utf8_lossy(&output.stderr).to_string())
-}
+/// Asks the user for the project folder with the native dialog and confines the file commands to it. The webview never names the folder, so it cannot widen the sandbox; `None` if the user cancelled.
+#[command] pub async fn open_workspace(title: String, app: AppHandle, state: State<'_, PeerState>) -> Result<Option<String>, WorkspaceError> { let (picked, folder) = tokio::sync::oneshot::channel(); app.dialog().file().set_title(title).pick_folder(move |folder| { let _ = picked.send(folder); }); let Some(folder) = folder.await.map_err(|e| e.to_string())? else { return Ok(None) }; let root = folder.into_path().map_err(|e| e.to_string())?; state.workspace.lock().unwrap_or_else(|e| e.into_inner()).open(&root)?; Ok(Some(root.to_string_lossy().to_string())) }
 
-}
+#[command] pub fn git_pull(path: String, ssh_key_path: String, state: State<'_, PeerState>) -> Result<String, WorkspaceError> { Ok(git::pull(&sandboxed(&state, &path)?, &ssh_key_path)?) }
 
-#[command] pub async fn request_join( peer_id: String, remote_addrs: Vec<String>, sender: SenderState<'_> ) -> Result<(), String> { let tx = sender.lock().await; tx.send(("join".to_string(), Payload::JoinCall { peer_id, remote_addrs })).await.map_err(|e| e.to_string()) }
+#[command] pub async fn request_join( peer_id: String, remote_addrs: Vec<String>, profile: JoinProfile, password: Option<String>, project_path: Option<String>, state: State<'_, PeerState>, sender: SenderState<'_> ) -> Result<(), String> { profile.validate()?; let tree = match project_path { Some(path) => { let path = sandboxed(&state, &path).map_err(|e| e.to_string())?; Some(tokio::task::spawn_blocking(move || ProjectTree::build(&path)).await.map_err(|e| e.to_string())?.map_err(|e| format!("Failed to hash local project: {}", e))?) }, None => None, }; dispatch(&sender, |reply| SwarmCommand::Join { peer_id, remote_addrs, profile, password, tree, reply }).await }
 
-#[command] pub async fn approve_join( peer_id: String, project_path: String, sender: SenderState<'_> ) -> Result<(), String> { let mut files = Vec::new(); let base_path = Path::new(&project_path);
+#[command] pub async fn approve_join( peer_id: String, project_path: String, role: Option<SessionRole>, state: State<'_, PeerState>, sender: SenderState<'_> ) -> Result<(), String> { let root = sandboxed(&state, &project_path).map_err(|e| e.to_string())?;
 
-if base_path.exists() {
-    visit_dirs(base_path, base_path, &mut files).map_err(|e| format!("Failed to pack project: {}", e))?;
-}
+let project = root.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
 
-let content = serde_json::to_vec(&files).map_err(|e| e.to_string())?;
-
-let tx = sender.lock().await;
-tx.send(("accept".to_string(), Payload::JoinAccept { peer_id, content })).await.map_err(|e| e.to_string())
+dispatch(&sender, |reply| SwarmCommand::AcceptJoin { peer_id, root, project, role: role.unwrap_or_default(), reply }).await
 
 }
 
-#[command] pub fn save_incoming_project(dest_path: String, data: Vec<u8>) -> Result<(), String> { let files: Vec<FileSyncEntry> = serde_json::from_slice(&data).map_err(|e| format!("Invalid project data: {}", e))?; let root = Path::new(&dest_path);
-
-fs::create_dir_all(root).map_err(|e| e.to_string())?;
-
-for file in files {
-    let file_path = root.join(file.path);
-    if let Some(parent) = file_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-    }
-    fs::write(file_path, file.content).map_err(|e| e.to_string())?;
-}
+#[command] pub async fn set_peer_role( peer_id: String, role: SessionRole, sender: SenderState<'_> ) -> Result<(), String> { dispatch(&sender, |reply| SwarmCommand::SetPeerRole { peer_id, role, reply }).await }
 
-Ok(())
+#[command] pub async fn kick_peer( peer_id: String, reason: Option<String>, sender: SenderState<'_> ) -> Result<(), String> { dispatch(&sender, |reply| SwarmCommand::Kick { peer_id, reason, ban: false, reply }).await }
 
-}
+#[command] pub async fn ban_peer( peer_id: String, reason: Option<String>, sender: SenderState<'_> ) -> Result<(), String> { dispatch(&sender, |reply| SwarmCommand::Kick { peer_id, reason, ban: true, reply }).await }
 
-#[command] pub async fn broadcast_update( path: String, data: Vec<u8>, sender: SenderState<'_> ) -> Result<(), String> { let tx = sender.lock().await; tx.send(("sync".to_string(), Payload::SyncData { path, data })).await.map_err(|e| e.to_string()) }
+#[command] pub fn get_session_role(state: State<'_, PeerState>) -> Result<Option<SessionRole>, String> { Ok(*state.local_role.lock().unwrap_or_else(|e| e.into_inner())) }
 
-#[command] pub async fn request_file_sync( path: String, sender: SenderState<'_> ) -> Result<(), String> { let tx = sender.lock().await; tx.send(("request_sync".to_string(), Payload::RequestSync { path })).await.map_err(|e| e.to_string()) }
+#[command] pub async fn reject_join( peer_id: String, reason: Option<String>, sender: SenderState<'_> ) -> Result<(), String> { dispatch(&sender, |reply| SwarmCommand::RejectJoin { peer_id, reason, reply }).await }
 
-#[command] pub async fn broadcast_file_content( path: String, data: Vec<u8>, sender: SenderState<'_> ) -> Result<(), String> { let tx = sender.lock().await; tx.send(("file_content".to_string(), Payload::FileContent { path, data })).await.map_err(|e| e.to_string()) }
+#[command] pub fn save_incoming_project(dest_path: String, transfer_id: String, app: AppHandle, state: State<'_, PeerState>) -> Result<ImportReport, WorkspaceError> { let dest_path = sandboxed(&state, &dest_path)?; let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?; Ok(files::save_incoming_project(&dest_path, &transfer::staged_project(&app_data_dir, &transfer_id)?)?) }
 
-#[command] pub fn read_directory(path: String) -> Result<Vec<FileEntry>, String> { let paths = fs::read_dir(path).map_err(|e| e.to_string())?; let mut entries = Vec::new();
+#[command] pub async fn broadcast_update( path: String, data: Vec<u8>, sender: SenderState<'_> ) -> Result<(), String> { dispatch(&sender, |reply| SwarmCommand::Sync { path, data, reply }).await }
 
-for path in paths {
-    let path = path.map_err(|e| e.to_string())?;
-    let file_type = path.file_type().map_err(|e| e.to_string())?;
-    let file_name = path.file_name().into_string().map_err(|_| "Invalid UTF-8".to_string())?;
-    let file_path = path.path().to_string_lossy().to_string();
-    
-    if file_name.starts_with('.') && file_name != ".collab_meta.json" { continue; } 
+#[command] pub async fn request_file_sync( path: String, sender: SenderState<'_> ) -> Result<(), String> { dispatch(&sender, |reply| SwarmCommand::RequestSync { path, reply }).await }
 
-    entries.push(FileEntry {
-        name: file_name,
-        path: file_path,
-        is_dir: file_type.is_dir(),
-    });
-}
+#[command] pub async fn broadcast_file_content( path: String, data: Vec<u8>, sender: SenderState<'_> ) -> Result<(), String> { dispatch(&sender, |reply| SwarmCommand::FileContent { path, data, reply }).await }
 
-entries.sort_by(|a, b| {
-    if a.is_dir == b.is_dir {
-        a.name.cmp(&b.name)
-    } else {
-        if a.is_dir { std::cmp::Ordering::Less } else { std::cmp::Ordering::Greater }
-    }
-});
+#[command] pub fn read_directory(path: String, state: State<'_, PeerState>) -> Result<Vec<FileEntry>, WorkspaceError> { Ok(files::read_directory(&sandboxed(&state, &path)?)?) }
 
-Ok(entries)
+#[command] pub fn read_file_content(path: String, state: State<'_, PeerState>) -> Result<Vec<u8>, WorkspaceError> { Ok(files::read_file(&sandboxed(&state, &path)?)?) }
 
-}
+#[command] pub fn init_git_repo(path: String, state: State<'_, PeerState>) -> Result<String, WorkspaceError> { Ok(git::init_repo(&sandboxed(&state, &path)?.to_string_lossy())?) }
 
-#[command] pub fn read_file_content(path: String) -> Result<Vec<u8>, String> { fs::read(path).map_err(|e| e.to_string()) }
+#[command] pub fn write_file_content(path: String, content: Vec<u8>, state: State<'_, PeerState>) -> Result<(), WorkspaceError> { Ok(files::write_file(&sandboxed(&state, &path)?, &content)?) }
 
-#[command] pub fn init_git_repo(path: String) -> Result<String, String> { match Repository::init(&path) { Ok(_) => Ok(format!("Initialized Git repository in {}", path)), Err(e) => Err(format!("Failed to init repo: {}", e)), } }
+#[command] pub fn get_remote_origin(path: String, state: State<'_, PeerState>) -> Result<String, WorkspaceError> { Ok(git::get_remote_origin(&sandboxed(&state, &path)?.to_string_lossy())?) }
 
-#[command] pub fn write_file_content(path: String, content: Vec<u8>) -> Result<(), String> { if let Some(parent) = Path::new(&path).parent() { fs::create_dir_all(parent).ok(); }
+#[command] pub fn set_remote_origin(path: String, url: String, state: State<'_, PeerState>) -> Result<String, WorkspaceError> { Ok(git::set_remote_origin(&sandboxed(&state, &path)?.to_string_lossy(), &url)?) }
 
-fs::write(&path, &content).map_err(|e| e.to_string())?;
+#[command] pub fn push_changes(path: String, ssh_key_path: String, state: State<'_, PeerState>) -> Result<String, WorkspaceError> { Ok(git::push(&sandboxed(&state, &path)?, &ssh_key_path)?) }
 
-if let Ok(repo) = Repository::discover(&path) {
-    let path_obj = Path::new(&path);
-    if let Some(workdir) = repo.workdir() {
-        if let Ok(relative_path) = path_obj.strip_prefix(workdir) {
-             let mut index = repo.index().map_err(|e| e.to_string())?;
-             index.add_path(relative_path).map_err(|_| "Failed to add path")?;
-             index.write().ok();
-        }
-    }
-}
+#[command] pub fn get_local_addrs(state: State<'_, PeerState>) -> Result<Vec<String>, String> { Ok(state.local_addrs.lock().unwrap_or_else(|e| e.into_inner()).clone()) }
 
-Ok(())
+#[command] pub fn get_network_config(state: State<'_, PeerState>) -> Result<NetworkConfig, String> { Ok(state.network_config.lock().unwrap_or_else(|e| e.into_inner()).clone()) }
 
-}
+/// Saves and applies `config`; returns whether some of it only takes effect after a restart.
+#[command] pub async fn update_network_config( config: NetworkConfig, app: AppHandle, state: State<'_, PeerState>, sender: SenderState<'_> ) -> Result<bool, String> { config.validate()?;
 
-#[command] pub fn get_remote_origin(path: String) -> Result<String, String> { let repo = Repository::open(&path).map_err(|e| e.to_string())?; let remote = repo.find_remote("origin").map_err(|_| "No remote 'origin' found".to_string())?; let url = remote.url().ok_or("Remote 'origin' has no URL")?; Ok(url.to_string()) }
+let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+config.save(&app_data_dir)?;
+let restart_required = std::mem::replace(&mut *state.network_config.lock().unwrap_or_else(|e| e.into_inner()), config.clone()).restart_required(&config);
 
-#[command] pub fn set_remote_origin(path: String, url: String) -> Result<String, String> { let repo = Repository::open(&path).map_err(|e| e.to_string())?; if repo.find_remote("origin").is_ok() { repo.remote_set_url("origin", &url).map_err(|e| e.to_string())?; } else { repo.remote("origin", &url).map_err(|e| e.to_string())?; } Ok(format!("Remote 'origin' set to {}", url)) }
+dispatch(&sender, |reply| SwarmCommand::UpdateNetworkConfig { config, reply }).await?;
+Ok(restart_required)
 
-#[command] pub fn push_changes(path: String, ssh_key_path: String) -> Result<String, String> { let mut commit_cmd = Command::new("git"); commit_cmd.current_dir(&path); commit_cmd.args(&["commit", "-m", "Auto-sync"]);
+}
 
-commit_cmd.env("GIT_TERMINAL_PROMPT", "0");
+#[command] pub fn list_nearby_hosts(state: State<'_, PeerState>) -> Result<Vec<DiscoveredPeer>, String> { let discovered = state.discovered_peers.lock().unwrap_or_else(|e| e.into_inner()); let mut hosts: Vec<DiscoveredPeer> = discovered.values().filter(|p| p.session.is_some()).cloned().collect(); hosts.sort_by(|a, b| a.peer_id.cmp(&b.peer_id)); Ok(hosts) }
 
-// [FIX] Apply env sanitization to commit command as well
-#[cfg(target_os = "linux")]
-{
-    commit_cmd.env_remove("DISPLAY");
-    commit_cmd.env_remove("GDK_BACKEND");
-    commit_cmd.env_remove("GTK_MODULES");
-    commit_cmd.env_remove("SSH_ASKPASS");
-    commit_cmd.env_remove("GIT_ASKPASS");
-    commit_cmd.env_remove("XAUTHORITY");
-}
+#[command] pub fn advertise_session(project_name: Option<String>, state: State<'_, PeerState>) -> Result<(), String> { *state.advertised_session.lock().unwrap_or_else(|e| e.into_inner()) = project_name; Ok(()) }
 
-#[cfg(not(target_os = "linux"))]
-{
-    commit_cmd.env("SSH_ASKPASS", "false");
-    commit_cmd.env("GIT_ASKPASS", "false");
-}
+#[command] pub fn set_session_password(password: Option<String>, state: State<'_, PeerState>) -> Result<(), String> { *state.session_password.lock().unwrap_or_else(|e| e.into_inner()) = password.filter(|p| !p.is_empty()); Ok(()) }
 
-let _ = commit_cmd.output();
+#[command] pub fn list_trusted_peers(state: State<'_, PeerState>) -> Result<Vec<TrustedPeer>, String> { Ok(state.trust.lock().unwrap_or_else(|e| e.into_inner()).entries()) }
 
-let mut cmd = Command::new("git");
-cmd.current_dir(&path);
-cmd.arg("push");
+#[command] pub fn set_peer_trust(peer_id: String, level: TrustLevel, note: Option<String>, app: AppHandle, state: State<'_, PeerState>) -> Result<(), String> { let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?; let mut trust = state.trust.lock().unwrap_or_else(|e| e.into_inner()); let mut updated = trust.clone(); updated.set(&peer_id, level, note.as_deref().unwrap_or_default())?; updated.save(&app_data_dir)?; *trust = updated; Ok(()) }
 
-if !ssh_key_path.trim().is_empty() {
-    #[cfg(target_os = "windows")]
-    let ssh_cmd = format!("ssh -i \"{}\"", ssh_key_path.replace("\\", "\\\\"));
-    #[cfg(not(target_os = "windows"))]
-    let ssh_cmd = format!("ssh -i \"{}\"", ssh_key_path);
-    cmd.env("GIT_SSH_COMMAND", ssh_cmd);
-}
+#[command] pub fn remove_peer_trust(peer_id: String, app: AppHandle, state: State<'_, PeerState>) -> Result<(), String> { let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?; let mut trust = state.trust.lock().unwrap_or_else(|e| e.into_inner()); let mut updated = trust.clone(); if !updated.remove(&peer_id) { return Err(format!("{} is not on the trust list", peer_id)); } updated.save(&app_data_dir)?; *trust = updated; Ok(()) }
 
-cmd.env("GIT_TERMINAL_PROMPT", "0");
-
-// [FIX] Apply env sanitization to push command
-#[cfg(target_os = "linux")]
-{
-    cmd.env_remove("DISPLAY");
-    cmd.env_remove("GDK_BACKEND");
-    cmd.env_remove("GTK_MODULES");
-    cmd.env_remove("SSH_ASKPASS");
-    cmd.env_remove("GIT_ASKPASS");
-    cmd.env_remove("SSH_AUTH_SOCK");
-    cmd.env_remove("XAUTHORITY");
-}
+#[command] pub fn list_session_peers(state: State<'_, PeerState>) -> Result<Vec<SessionPeer>, String> { let members = state.session_peers.lock().unwrap_or_else(|e| e.into_inner()); let mut peers: Vec<SessionPeer> = members.values().cloned().collect(); peers.sort_by(|a, b| a.peer_id.cmp(&b.peer_id)); Ok(peers) }
 
-#[cfg(not(target_os = "linux"))]
-{
-    cmd.env("SSH_ASKPASS", "false");
-    cmd.env("GIT_ASKPASS", "false");
-}
+#[command] pub fn get_document_state(path: String, state_vector: Option<Vec<u8>>, state: State<'_, PeerState>) -> Result<Option<Vec<u8>>, String> { state.documents.lock().unwrap_or_else(|e| e.into_inner()).diff(&path, &state_vector.unwrap_or_default()) }
 
-let output = cmd.output().map_err(|e| format!("Git command failed: {}", e))?;
+#[command] pub async fn set_awareness_state( client_id: u64, state: Option<String>, sender: SenderState<'_> ) -> Result<(), String> { dispatch(&sender, |reply| SwarmCommand::SetAwareness { client_id, state, reply }).await }
 
-if output.status.success() {
-    Ok("Push successful".to_string())
-} else {
-    Err(String::from_utf8_lossy(&output.stderr).to_string())
-}
+#[command] pub fn get_awareness_states(state: State<'_, PeerState>) -> Result<Vec<AwarenessEntry>, String> { Ok(state.awareness.lock().unwrap_or_else(|e| e.into_inner()).states()) }
 
-}
+#[command] pub async fn send_chat_message( text: String, sender: SenderState<'_> ) -> Result<(), String> { dispatch(&sender, |reply| SwarmCommand::Chat { text, reply }).await }
 
-#[command] pub fn get_local_addrs(state: State<'_, PeerState>) -> Result<Vec<String>, String> { Ok(state.local_addrs.lock().unwrap_or_else(|e| e.into_inner()).clone()) }
\ No newline at end of file
+#[command] pub async fn cancel_transfer( peer_id: String, sender: SenderState<'_> ) -> Result<(), String> { dispatch(&sender, |reply| SwarmCommand::CancelTransfer { peer_id, reply }).await }
@@ -1,20 +1,26 @@
-mod protocol;
-mod state;
-mod network;
 mod commands;
 
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use crate::state::PeerState;
-use crate::protocol::Payload;
+use my_editor_core::{EventSink, Events, PeerState, SwarmCommand};
 use tauri::http::{Response, StatusCode};
+use tauri::{AppHandle, Emitter};
 use tauri::Manager; // [FIX] Required to access .path() and .app_handle()
 
+/// Forwards core events to the webview.
+struct TauriEvents(AppHandle);
+
+impl EventSink for TauriEvents {
+    fn emit(&self, event: &str, payload: serde_json::Value) {
+        let _ = self.0.emit(event, payload);
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let peer_state = PeerState::new();
 
-    let (tx, rx) = tokio::sync::mpsc::channel::<(String, Payload)>(32);
+    let (tx, rx) = tokio::sync::mpsc::channel::<SwarmCommand>(32);
     let tx = Arc::new(Mutex::new(tx));
 
     tauri::Builder::default()
@@ -39,17 +45,38 @@ pub fn run() {
         .setup(move |app| {
             let handle = app.handle().clone();
             let state_for_thread = peer_state.clone();
+            let app_data_dir = handle.path().app_data_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
+
+            // File commands may reach the plugins and settings folders without a project open.
+            // Only dedicated subfolders: on macOS and Windows the config dir is the app data
+            // dir, which holds the node identity, trust list and network config.
+            {
+                let mut workspace = peer_state.workspace.lock().unwrap_or_else(|e| e.into_inner());
+                if let Ok(dir) = handle.path().app_local_data_dir() {
+                    workspace.allow(dir.join("plugins"));
+                }
+                if let Ok(dir) = handle.path().app_config_dir() {
+                    workspace.allow(dir.join("settings"));
+                }
+            }
 
             tauri::async_runtime::spawn(async move {
-                if let Err(e) = network::start_p2p_node(handle, state_for_thread, rx).await {
+                if let Err(e) = my_editor_core::start_p2p_node(Events::new(TauriEvents(handle)), app_data_dir, state_for_thread, rx).await {
                     eprintln!("P2P Network Error: {e}");
                 }
             });
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            commands::open_workspace,
             commands::request_join,
             commands::approve_join,
+            commands::reject_join,
+            commands::set_peer_role,
+            commands::get_session_role,
+            commands::kick_peer,
+            commands::ban_peer,
+            commands::set_session_password,
             commands::broadcast_update,
             commands::read_directory,
             commands::read_file_content,
@@ -63,7 +90,20 @@ pub fn run() {
             commands::broadcast_file_content,
             commands::get_local_peer_id,
             commands::git_pull,
-            commands::get_local_addrs
+            commands::get_local_addrs,
+            commands::get_network_config,
+            commands::update_network_config,
+            commands::list_nearby_hosts,
+            commands::advertise_session,
+            commands::list_session_peers,
+            commands::list_trusted_peers,
+            commands::set_peer_trust,
+            commands::remove_peer_trust,
+            commands::get_document_state,
+            commands::set_awareness_state,
+            commands::get_awareness_states,
+            commands::send_chat_message,
+            commands::cancel_transfer
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");